@@ -0,0 +1,38 @@
+use super::*;
+
+pub fn run(opt: &Opt, mut model: Model) {
+    if let Some(path) = &opt.script {
+        let file = std::fs::File::open(path).expect("Failed to open script");
+        let script: Vec<ReplayMessage> =
+            serde_json::from_reader(file).expect("Failed to parse script");
+        model = Model::from_replay(Replay {
+            seed: model.seed(),
+            rules: model.rules.clone(),
+            messages: script,
+        });
+    }
+
+    for _ in 0..opt.ticks {
+        if model.game_over {
+            break;
+        }
+        model.tick();
+    }
+
+    if let Some(path) = &opt.record {
+        model.replay.save(path).expect("Failed to save replay");
+    }
+
+    println!("Seed: {}", model.seed());
+    println!("Ticks: {}", model.ticks);
+    println!("Depth: {}", model.current_depth);
+    for &nutrient in &Nutrient::ALL {
+        println!("{}: {}", nutrient.name(), model.nutrients.get(nutrient));
+    }
+    println!("Water: {}", model.water);
+    println!("Roots: {}", model.tree_roots.roots.len());
+    println!("Tiles: {}", model.tiles.len());
+    println!("Minerals gathered: {}", model.stats.minerals_gathered);
+    println!("Branches grown: {}", model.stats.branches_grown);
+    println!("Game over: {}", model.game_over);
+}
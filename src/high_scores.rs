@@ -0,0 +1,86 @@
+use super::*;
+
+const FILE_NAME: &str = "high_scores.json";
+const MAX_SCORES: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighScore {
+    pub depth: f32,
+    pub minerals: f32,
+    pub seed: u64,
+    /// Simulated time in seconds
+    pub duration: f32,
+    pub date: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    /// Sorted by depth, deepest first
+    pub scores: Vec<HighScore>,
+}
+
+impl HighScores {
+    pub fn load() -> Self {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => return Self::default(),
+        };
+        match std::fs::File::open(&path) {
+            Ok(file) => {
+                serde_json::from_reader(std::io::BufReader::new(file)).unwrap_or_else(|e| {
+                    error!("Failed to parse high scores {:?}: {}", path, e);
+                    Self::default()
+                })
+            }
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow!("No data directory found"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    pub fn add(&mut self, score: HighScore) {
+        let index = self
+            .scores
+            .iter()
+            .position(|other| other.depth < score.depth)
+            .unwrap_or(self.scores.len());
+        self.scores.insert(index, score);
+        self.scores.truncate(MAX_SCORES);
+    }
+
+    pub fn best(&self) -> Option<&HighScore> {
+        self.scores.first()
+    }
+
+    pub fn best_for_seed(&self, seed: u64) -> Option<&HighScore> {
+        self.scores.iter().find(|score| score.seed == seed)
+    }
+
+    pub fn top(&self, count: usize) -> &[HighScore] {
+        &self.scores[..count.min(self.scores.len())]
+    }
+
+    fn path() -> Option<std::path::PathBuf> {
+        data_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")).join(FILE_NAME))
+    }
+}
+
+fn data_dir() -> Option<std::path::PathBuf> {
+    let env_dir = |name: &str| std::env::var_os(name).map(std::path::PathBuf::from);
+    if cfg!(target_os = "windows") {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_dir("XDG_DATA_HOME")
+            .or_else(|| env_dir("HOME").map(|home| home.join(".local").join("share")))
+    }
+}
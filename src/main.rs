@@ -1,35 +1,119 @@
 use geng::prelude::*;
 
+mod headless;
+mod high_scores;
 mod model;
 mod renderer;
 
+use high_scores::*;
 use model::*;
 use renderer::*;
 
+const CONFIG_PATH: &str = "config.json";
+const SAVE_PATH: &str = "save.bin";
+const REPLAY_PATH: &str = "replay.json";
+
+#[derive(StructOpt)]
+struct Opt {
+    #[structopt(long)]
+    seed: Option<u64>,
+    /// Run the simulation without opening a window
+    #[structopt(long)]
+    headless: bool,
+    /// Number of fixed ticks to simulate in headless mode
+    #[structopt(long, default_value = "1000")]
+    ticks: u64,
+    /// Path to a json list of messages to apply in headless mode
+    #[structopt(long)]
+    script: Option<String>,
+    /// Path to a replay to play back
+    #[structopt(long)]
+    replay: Option<String>,
+    /// Path to write the replay to at the end of a headless run
+    #[structopt(long)]
+    record: Option<String>,
+}
+
 struct State {
     texture: Option<ugli::Texture>,
     model: Model,
     renderer: Renderer,
+    rules_watcher: RulesWatcher,
+    high_scores: HighScores,
+    score_recorded: bool,
 }
 
 impl State {
-    fn new(geng: &Rc<Geng>) -> Self {
+    fn new(geng: &Rc<Geng>, model: Model, rules_watcher: RulesWatcher) -> Self {
         Self {
             texture: None,
             renderer: Renderer::new(geng),
-            model: Model::new(),
+            model,
+            rules_watcher,
+            high_scores: HighScores::load(),
+            score_recorded: false,
+        }
+    }
+    fn record_score(&mut self) {
+        self.score_recorded = true;
+        // Replayed runs were already scored when they were played
+        if self.model.is_from_replay() {
+            return;
+        }
+        self.high_scores.add(HighScore {
+            depth: self.model.current_depth,
+            minerals: self.model.stats.minerals_gathered,
+            seed: self.model.seed(),
+            duration: self.model.ticks as f32 * self.model.fixed_delta_time(),
+            date: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        });
+        if let Err(e) = self.high_scores.save() {
+            error!("Failed to save high scores: {}", e);
         }
     }
     fn reset(&mut self) {
+        if !self.score_recorded && self.model.current_depth > 0.0 {
+            self.record_score();
+        }
+        self.score_recorded = false;
         self.renderer.reset(&mut self.texture);
+        if let Some(rules) = self.rules_watcher.poll() {
+            self.model.base_rules = rules;
+        }
         self.model.reset();
     }
+    fn save(&mut self) {
+        match self.model.save(SAVE_PATH) {
+            Ok(()) => info!("Saved game to {:?}", SAVE_PATH),
+            Err(e) => error!("Failed to save game to {:?}: {}", SAVE_PATH, e),
+        }
+    }
+    fn load(&mut self) {
+        match Model::load(SAVE_PATH) {
+            Ok(model) => {
+                self.renderer.reset(&mut self.texture);
+                self.score_recorded = model.game_over;
+                self.model = model;
+                info!("Loaded game from {:?}", SAVE_PATH);
+            }
+            Err(e) => error!("Failed to load game from {:?}: {}", SAVE_PATH, e),
+        }
+    }
+    fn save_replay(&self) {
+        match self.model.replay.save(REPLAY_PATH) {
+            Ok(()) => info!("Saved replay to {:?}", REPLAY_PATH),
+            Err(e) => error!("Failed to save replay to {:?}: {}", REPLAY_PATH, e),
+        }
+    }
 }
 
 impl geng::State for State {
     fn update(&mut self, delta_time: f64) {
         self.model.update(delta_time as f32);
         self.renderer.update(delta_time as f32);
+        if self.model.game_over && !self.score_recorded {
+            self.record_score();
+        }
     }
     fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
         let view = if self.renderer.request_view {
@@ -38,24 +122,45 @@ impl geng::State for State {
         } else {
             self.model.get_client_view_update()
         };
-        self.renderer.draw(framebuffer, &view, &mut self.texture);
+        self.renderer
+            .draw(framebuffer, &view, &self.high_scores, &mut self.texture);
     }
     fn handle_event(&mut self, event: geng::Event) {
         match event {
             geng::Event::KeyDown { key: geng::Key::R } => self.reset(),
+            geng::Event::KeyDown { key: geng::Key::S } => self.save(),
+            geng::Event::KeyDown { key: geng::Key::L } => self.load(),
+            geng::Event::KeyDown { key: geng::Key::P } => self.save_replay(),
             _ => (),
         }
 
         self.model.handle_event(&event);
         if let Some(message) = self.renderer.handle_event(&event) {
-            self.model.handle_message(message);
+            // Player input would desync a replay that is still playing
+            if !self.model.is_replaying() {
+                self.model.handle_message(message);
+            }
         }
     }
 }
 
 fn main() {
     geng::setup_panic_handler();
+    let opt: Opt = program_args::parse();
+    let mut rules_watcher = RulesWatcher::new(CONFIG_PATH);
+    let rules = rules_watcher.poll().unwrap_or_default();
+    let model = match &opt.replay {
+        Some(path) => Model::from_replay(Replay::load(path).expect("Failed to load replay")),
+        None => match opt.seed {
+            Some(seed) => Model::with_seed(rules, seed),
+            None => Model::new(rules),
+        },
+    };
+    if opt.headless {
+        headless::run(&opt, model);
+        return;
+    }
     let geng = Rc::new(Geng::new(default()));
-    let state = State::new(&geng);
+    let state = State::new(&geng, model, rules_watcher);
     geng::run(geng, state);
 }
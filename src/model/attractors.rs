@@ -0,0 +1,172 @@
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attractor {
+    pub position: Vec2<f32>,
+    pub kind: AttractorKind,
+    /// The head pulled by the attractor, repulsors push every head around them instead
+    pub root: Option<Id>,
+    /// Seconds left before a timed attractor disappears
+    pub time_left: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttractorKind {
+    #[default]
+    Weak,
+    Strong,
+    /// Pushes away every head within its radius
+    Repulsor,
+    /// Disappears after its lifetime
+    Timed,
+}
+
+impl AttractorKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            AttractorKind::Weak => "Weak attractor",
+            AttractorKind::Strong => "Strong attractor",
+            AttractorKind::Repulsor => "Repulsor",
+            AttractorKind::Timed => "Timed attractor",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttractorRules {
+    pub kind: AttractorKind,
+    pub cost: Nutrients,
+    /// Steering force relative to the root inertia
+    pub strength: f32,
+    /// Distance at which a repulsor affects heads
+    #[serde(default)]
+    pub radius: f32,
+    /// Seconds before the attractor disappears, if ever
+    #[serde(default)]
+    pub lifetime: Option<f32>,
+}
+
+pub fn default_attractors() -> Vec<AttractorRules> {
+    vec![
+        AttractorRules {
+            kind: AttractorKind::Weak,
+            cost: Nutrients {
+                phosphorus: 1.0,
+                ..default()
+            },
+            strength: 0.5,
+            radius: 0.0,
+            lifetime: None,
+        },
+        AttractorRules {
+            kind: AttractorKind::Strong,
+            cost: Nutrients {
+                phosphorus: 3.0,
+                ..default()
+            },
+            strength: 2.0,
+            radius: 0.0,
+            lifetime: None,
+        },
+        AttractorRules {
+            kind: AttractorKind::Repulsor,
+            cost: Nutrients {
+                phosphorus: 2.0,
+                ..default()
+            },
+            strength: 1.5,
+            radius: 5.0,
+            lifetime: Some(15.0),
+        },
+        AttractorRules {
+            kind: AttractorKind::Timed,
+            cost: Nutrients {
+                phosphorus: 1.0,
+                ..default()
+            },
+            strength: 1.5,
+            radius: 0.0,
+            lifetime: Some(5.0),
+        },
+    ]
+}
+
+impl Rules {
+    pub fn attractor(&self, kind: AttractorKind) -> Option<&AttractorRules> {
+        self.attractors
+            .iter()
+            .find(|attractor_rules| attractor_rules.kind == kind)
+    }
+}
+
+impl Model {
+    pub fn spawn_attractor(&mut self, position: Vec2<f32>, kind: AttractorKind) {
+        let attractor_rules = match self.rules.attractor(kind) {
+            Some(attractor_rules) => attractor_rules.clone(),
+            None => return,
+        };
+        let root = match kind {
+            AttractorKind::Repulsor => None,
+            _ => match Self::closest_root_id(&self.tree_roots, position) {
+                Some(id) => Some(id),
+                None => return,
+            },
+        };
+        if !self.nutrients.try_spend(&attractor_rules.cost) {
+            return;
+        }
+        self.tree_roots.attractors.push(Attractor {
+            position,
+            kind,
+            root,
+            time_left: attractor_rules.lifetime,
+        });
+    }
+
+    /// Counts down the lifetime of timed attractors, removing the expired ones.
+    pub(super) fn update_attractors(&mut self) {
+        let delta_time = self.fixed_delta_time;
+        self.tree_roots.attractors.retain_mut(|attractor| {
+            if let Some(time_left) = &mut attractor.time_left {
+                *time_left -= delta_time;
+                return *time_left > 0.0;
+            }
+            true
+        });
+    }
+
+    /// The steering force applied to a head by the attractors around it.
+    /// Attractors that the head has passed are removed.
+    pub(super) fn attractor_steering(&mut self, position: Vec2<f32>, root_id: Id) -> Vec2<f32> {
+        let mut steering = vec2(0.0, 0.0);
+        let rules = &self.rules;
+        self.tree_roots.attractors.retain(|attractor| {
+            let strength = rules
+                .attractor(attractor.kind)
+                .map_or(0.0, |rules| rules.strength);
+            let delta = attractor.position - position;
+            match attractor.kind {
+                AttractorKind::Repulsor => {
+                    let radius = rules
+                        .attractor(attractor.kind)
+                        .map_or(0.0, |rules| rules.radius);
+                    if delta.len() < radius && delta.len() > 0.0 {
+                        steering -= delta.normalize() * strength;
+                    }
+                    true
+                }
+                _ if attractor.root == Some(root_id) => {
+                    if delta.y < 0.0 {
+                        return false;
+                    }
+                    if delta.len() > 0.0 {
+                        steering += delta.normalize() * strength;
+                    }
+                    true
+                }
+                _ => true,
+            }
+        });
+        steering
+    }
+}
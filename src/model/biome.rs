@@ -0,0 +1,409 @@
+use super::*;
+
+/// A depth band with its own generation parameters and colors.
+/// A biome lasts from its `depth_start` until the next biome begins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Biome {
+    pub name: String,
+    pub depth_start: i32,
+    pub noise_layers: Vec<NoiseLayer>,
+    /// Checked in order, the first matching rule decides the tile.
+    /// Tiles matching no rule are dirt.
+    pub tiles: Vec<TileRule>,
+    pub palette: Palette,
+}
+
+/// A named noise field. Layers with the same name share a seed,
+/// so that the terrain stays continuous across biome boundaries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoiseLayer {
+    pub name: String,
+    #[serde(default)]
+    pub properties: MultiNoiseProperties,
+}
+
+/// Places `tile` wherever all of the conditions hold.
+/// Resource tiles use their amount as the richness, which is reached
+/// at the `min` end of the first condition and fades towards `max`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TileRule {
+    pub tile: Tile,
+    #[serde(default)]
+    pub conditions: Vec<NoiseCondition>,
+}
+
+/// Holds when the value of the layer lies within `min..=max`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoiseCondition {
+    pub layer: String,
+    pub min: f32,
+    pub max: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    pub dirt: Color<f32>,
+    pub stone: Color<f32>,
+    pub clay: Color<f32>,
+    pub gravel: Color<f32>,
+    pub bedrock: Color<f32>,
+    pub toxic: Color<f32>,
+    pub air: Color<f32>,
+    pub nitrogen: Color<f32>,
+    pub phosphorus: Color<f32>,
+    pub potassium: Color<f32>,
+    pub water: Color<f32>,
+}
+
+/// Two neighbouring biomes mixed together, `t` being the weight of the second one.
+#[derive(Debug, Clone, Copy)]
+pub struct BiomeSample {
+    pub from: usize,
+    pub to: usize,
+    pub t: f32,
+}
+
+impl Default for Biome {
+    fn default() -> Self {
+        Self {
+            name: "Topsoil".to_owned(),
+            depth_start: 0,
+            noise_layers: vec![
+                NoiseLayer::new("terrain", MultiNoiseProperties::default()),
+                NoiseLayer::new(
+                    "nitrogen",
+                    MultiNoiseProperties {
+                        scale: 5.0,
+                        ..default()
+                    },
+                ),
+                NoiseLayer::new(
+                    "phosphorus",
+                    MultiNoiseProperties {
+                        scale: 5.0,
+                        ..default()
+                    },
+                ),
+                NoiseLayer::new(
+                    "potassium",
+                    MultiNoiseProperties {
+                        scale: 5.0,
+                        ..default()
+                    },
+                ),
+                NoiseLayer::new(
+                    "water",
+                    MultiNoiseProperties {
+                        scale: 10.0,
+                        ..default()
+                    },
+                ),
+                NoiseLayer::new(
+                    "caves",
+                    MultiNoiseProperties {
+                        scale: 8.0,
+                        octaves: 2,
+                        lacunarity: 2.0,
+                        persistance: 0.5,
+                        ..default()
+                    },
+                ),
+            ],
+            tiles: vec![
+                TileRule::new(Tile::Nitrogen { amount: 2.0 }, "nitrogen", 0.0, 0.03),
+                TileRule::new(Tile::Phosphorus { amount: 2.0 }, "phosphorus", 0.0, 0.03),
+                TileRule::new(Tile::Potassium { amount: 2.0 }, "potassium", 0.0, 0.03),
+                TileRule::new(Tile::Water { amount: 3.0 }, "water", 0.0, 0.05),
+                TileRule::new(Tile::Toxic, "nitrogen", 0.95, 1.0),
+                TileRule::new(Tile::Air, "caves", 0.0, 0.03),
+                TileRule::new(Tile::Stone, "terrain", 0.0, 0.2),
+                TileRule::new(Tile::Clay, "terrain", 0.2, 0.3),
+                TileRule::new(Tile::Gravel, "terrain", 0.9, 1.0),
+            ],
+            palette: Palette::default(),
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            dirt: Color::rgb(0.5, 0.5, 0.0),
+            stone: Color::GRAY,
+            clay: Color::rgb(0.6, 0.35, 0.2),
+            gravel: Color::rgb(0.65, 0.6, 0.5),
+            bedrock: Color::rgb(0.15, 0.15, 0.2),
+            toxic: Color::rgb(0.4, 0.8, 0.1),
+            air: Color::rgb(0.05, 0.05, 0.05),
+            nitrogen: Color::rgb(0.3, 0.4, 1.0),
+            phosphorus: Color::rgb(1.0, 0.9, 0.3),
+            potassium: Color::rgb(0.9, 0.4, 0.9),
+            water: Color::rgb(0.0, 0.3, 0.8),
+        }
+    }
+}
+
+impl NoiseLayer {
+    pub fn new(name: &str, properties: MultiNoiseProperties) -> Self {
+        Self {
+            name: name.to_owned(),
+            properties,
+        }
+    }
+}
+
+impl TileRule {
+    pub fn new(tile: Tile, layer: &str, min: f32, max: f32) -> Self {
+        Self {
+            tile,
+            conditions: vec![NoiseCondition {
+                layer: layer.to_owned(),
+                min,
+                max,
+            }],
+        }
+    }
+}
+
+pub fn default_biomes() -> Vec<Biome> {
+    let mut clay_beds = Biome {
+        name: "Clay beds".to_owned(),
+        depth_start: 150,
+        tiles: vec![
+            TileRule::new(Tile::Bedrock, "terrain", 0.0, 0.02),
+            TileRule::new(Tile::Nitrogen { amount: 3.0 }, "nitrogen", 0.0, 0.04),
+            TileRule::new(Tile::Phosphorus { amount: 3.0 }, "phosphorus", 0.0, 0.04),
+            TileRule::new(Tile::Potassium { amount: 3.0 }, "potassium", 0.0, 0.04),
+            TileRule::new(Tile::Water { amount: 3.0 }, "water", 0.0, 0.07),
+            TileRule::new(Tile::Toxic, "nitrogen", 0.95, 1.0),
+            TileRule::new(Tile::Air, "caves", 0.0, 0.06),
+            TileRule::new(Tile::Stone, "terrain", 0.0, 0.2),
+            TileRule::new(Tile::Clay, "terrain", 0.2, 0.45),
+            TileRule::new(Tile::Gravel, "terrain", 0.95, 1.0),
+        ],
+        palette: Palette {
+            dirt: Color::rgb(0.55, 0.4, 0.15),
+            clay: Color::rgb(0.7, 0.35, 0.15),
+            ..default()
+        },
+        ..default()
+    };
+    clay_beds.noise_layers[0] = NoiseLayer::new(
+        "terrain",
+        MultiNoiseProperties {
+            scale: 15.0,
+            octaves: 2,
+            lacunarity: 2.0,
+            persistance: 0.5,
+            ..default()
+        },
+    );
+    let mut deep_rock = Biome {
+        name: "Deep rock".to_owned(),
+        depth_start: 400,
+        tiles: vec![
+            TileRule::new(Tile::Bedrock, "terrain", 0.0, 0.08),
+            TileRule::new(Tile::Nitrogen { amount: 4.0 }, "nitrogen", 0.0, 0.05),
+            TileRule::new(Tile::Phosphorus { amount: 4.0 }, "phosphorus", 0.0, 0.05),
+            TileRule::new(Tile::Potassium { amount: 4.0 }, "potassium", 0.0, 0.05),
+            TileRule::new(Tile::Water { amount: 2.0 }, "water", 0.0, 0.03),
+            TileRule::new(Tile::Toxic, "nitrogen", 0.92, 1.0),
+            TileRule::new(Tile::Air, "caves", 0.0, 0.1),
+            TileRule::new(Tile::Stone, "terrain", 0.0, 0.35),
+            TileRule::new(Tile::Clay, "terrain", 0.35, 0.4),
+            TileRule::new(Tile::Gravel, "terrain", 0.85, 1.0),
+        ],
+        palette: Palette {
+            dirt: Color::rgb(0.35, 0.3, 0.3),
+            stone: Color::rgb(0.4, 0.4, 0.45),
+            ..default()
+        },
+        ..default()
+    };
+    // Cellular noise breaks the rock into boulders
+    deep_rock.noise_layers[0] = NoiseLayer::new(
+        "terrain",
+        MultiNoiseProperties {
+            algorithm: NoiseAlgorithm::Worley,
+            scale: 6.0,
+            ..default()
+        },
+    );
+    vec![Biome::default(), clay_beds, deep_rock]
+}
+
+impl Biome {
+    /// Checks that every condition refers to a layer of this biome.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for rule in &self.tiles {
+            for condition in &rule.conditions {
+                if !self
+                    .noise_layers
+                    .iter()
+                    .any(|layer| layer.name == condition.layer)
+                {
+                    anyhow::bail!(
+                        "Biome {:?} has no noise layer named {:?}",
+                        self.name,
+                        condition.layer
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The largest amount a generated tile of the same kind can hold.
+    pub fn richness(&self, tile: &Tile) -> Option<f32> {
+        self.tiles.iter().find_map(|rule| {
+            if std::mem::discriminant(&rule.tile) == std::mem::discriminant(tile) {
+                rule.tile.resource()
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Finds the biome at the given depth, mixing neighbours within
+/// `blend / 2` of the boundary between them.
+/// Expects the biomes to be sorted by `depth_start`.
+pub fn sample_biomes(biomes: &[Biome], blend: f32, depth: f32) -> BiomeSample {
+    for (index, biome) in biomes.iter().enumerate().skip(1) {
+        let offset = depth - biome.depth_start as f32 + blend / 2.0;
+        if offset >= 0.0 && offset < blend {
+            let t = offset / blend;
+            return BiomeSample {
+                from: index - 1,
+                to: index,
+                t: t * t * (3.0 - 2.0 * t),
+            };
+        }
+    }
+    let index = biomes
+        .iter()
+        .rposition(|biome| biome.depth_start as f32 <= depth)
+        .unwrap_or(0);
+    BiomeSample {
+        from: index,
+        to: index,
+        t: 0.0,
+    }
+}
+
+/// Mixes the tile rules of two biomes.
+/// Rule lists of the same shape are interpolated rule by rule,
+/// otherwise the rules of the closer biome are used.
+pub fn lerp_tile_rules(from: &[TileRule], to: &[TileRule], t: f32) -> Vec<TileRule> {
+    let same_shape = from.len() == to.len()
+        && from.iter().zip(to).all(|(a, b)| {
+            std::mem::discriminant(&a.tile) == std::mem::discriminant(&b.tile)
+                && a.conditions.len() == b.conditions.len()
+                && a.conditions
+                    .iter()
+                    .zip(&b.conditions)
+                    .all(|(a, b)| a.layer == b.layer)
+        });
+    if !same_shape {
+        return if t < 0.5 { from } else { to }.to_vec();
+    }
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    from.iter()
+        .zip(to)
+        .map(|(a, b)| {
+            let mut tile = a.tile.clone();
+            if let (Some(amount), Some(other)) = (tile.resource_mut(), b.tile.resource()) {
+                *amount = lerp(*amount, other);
+            }
+            TileRule {
+                tile,
+                conditions: a
+                    .conditions
+                    .iter()
+                    .zip(&b.conditions)
+                    .map(|(a, b)| NoiseCondition {
+                        layer: a.layer.clone(),
+                        min: lerp(a.min, b.min),
+                        max: lerp(a.max, b.max),
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Picks the tile of the first rule whose conditions all hold.
+pub fn tile_from_rules(rules: &[TileRule], noise: impl Fn(&str) -> f32) -> Tile {
+    for rule in rules {
+        let holds = rule.conditions.iter().all(|condition| {
+            let value = noise(&condition.layer);
+            condition.min <= value && value <= condition.max
+        });
+        if !holds {
+            continue;
+        }
+        let mut tile = rule.tile.clone();
+        if let (Some(amount), Some(condition)) = (tile.resource_mut(), rule.conditions.first()) {
+            let range = condition.max - condition.min;
+            let strength = if range > 0.0 {
+                1.0 - (noise(&condition.layer) - condition.min) / range
+            } else {
+                1.0
+            };
+            *amount = (strength * *amount).clamp(0.5_f32.min(*amount), *amount);
+        }
+        return tile;
+    }
+    Tile::Dirt
+}
+
+impl Palette {
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            dirt: lerp_color(self.dirt, other.dirt, t),
+            stone: lerp_color(self.stone, other.stone, t),
+            clay: lerp_color(self.clay, other.clay, t),
+            gravel: lerp_color(self.gravel, other.gravel, t),
+            bedrock: lerp_color(self.bedrock, other.bedrock, t),
+            toxic: lerp_color(self.toxic, other.toxic, t),
+            air: lerp_color(self.air, other.air, t),
+            nitrogen: lerp_color(self.nitrogen, other.nitrogen, t),
+            phosphorus: lerp_color(self.phosphorus, other.phosphorus, t),
+            potassium: lerp_color(self.potassium, other.potassium, t),
+            water: lerp_color(self.water, other.water, t),
+        }
+    }
+}
+
+pub fn lerp_color(a: Color<f32>, b: Color<f32>, t: f32) -> Color<f32> {
+    Color::rgba(
+        a.r + (b.r - a.r) * t,
+        a.g + (b.g - a.g) * t,
+        a.b + (b.b - a.b) * t,
+        a.a + (b.a - a.a) * t,
+    )
+}
+
+/// Noise generators for every layer of every biome.
+pub fn biome_noises(biomes: &[Biome], seed: u32) -> Vec<Vec<MultiNoise>> {
+    biomes
+        .iter()
+        .map(|biome| {
+            biome
+                .noise_layers
+                .iter()
+                .map(|layer| MultiNoise::new(layer_seed(seed, &layer.name), &layer.properties))
+                .collect()
+        })
+        .collect()
+}
+
+/// Derives the seed of a layer from its name, so that it does not depend on the layer order.
+fn layer_seed(seed: u32, name: &str) -> u32 {
+    // FNV-1a
+    name.bytes().fold(2166136261 ^ seed, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(16777619)
+    })
+}
@@ -2,19 +2,32 @@ use super::*;
 #[derive(Debug, Clone)]
 pub struct ClientView {
     pub rules: Rules,
+    pub seed: u64,
     pub current_depth: f32,
-    pub minerals: f32,
+    pub nutrients: Nutrients,
+    pub upgrade_levels: HashMap<Upgrade, usize>,
+    pub water: f32,
+    pub drilling: bool,
+    pub stats: RunStats,
+    pub game_over: bool,
     pub tiles: HashMap<Position, ViewEvent<Tile>>,
     pub roots: HashMap<Id, ViewEvent<Root>>,
-    pub attractors: Vec<ViewEvent<Attractor>>,
+    /// Every attractor, they are redrawn each frame
+    pub attractors: Vec<Attractor>,
 }
 
 impl ClientView {
-    pub fn new(rules: Rules) -> Self {
+    pub fn new(rules: Rules, seed: u64) -> Self {
         Self {
             rules,
+            seed,
             current_depth: 0.0,
-            minerals: 0.0,
+            nutrients: Nutrients::default(),
+            upgrade_levels: HashMap::new(),
+            water: 0.0,
+            drilling: false,
+            stats: RunStats::default(),
+            game_over: false,
             tiles: HashMap::new(),
             roots: HashMap::new(),
             attractors: Vec::new(),
@@ -31,11 +44,23 @@ impl Model {
     pub fn get_client_view(&self) -> ClientView {
         ClientView {
             rules: self.rules.clone(),
+            seed: self.seed,
             current_depth: self.current_depth,
-            minerals: self.minerals,
+            nutrients: self.nutrients,
+            upgrade_levels: self.upgrade_levels.clone(),
+            water: self.water,
+            drilling: self.drilling,
+            stats: self.stats.clone(),
+            game_over: self.game_over,
             tiles: {
+                // Rows above the deletion depth are about to be dropped, so they are left out.
+                // Veins and structures can reach below the generation depth, so there is no bottom
+                let area = self.get_area(
+                    self.current_depth as i32 - self.rules.deletion_depth,
+                    i32::MAX,
+                );
                 let mut tiles = HashMap::with_capacity(self.tiles.len());
-                for (&pos, tile) in &self.tiles {
+                for (pos, tile) in self.tiles.iter_area(area) {
                     tiles.insert(pos, ViewEvent::Changed(tile.clone()));
                 }
                 tiles
@@ -47,23 +72,23 @@ impl Model {
                 }
                 roots
             },
-            attractors: {
-                let mut attractors = Vec::with_capacity(self.tree_roots.attractors.len());
-                for attractor in &self.tree_roots.attractors {
-                    attractors.push(ViewEvent::Changed(attractor.clone()));
-                }
-                attractors
-            },
+            attractors: self.tree_roots.attractors.clone(),
         }
     }
     pub fn get_client_view_update(&mut self) -> ClientView {
         ClientView {
             rules: self.rules.clone(),
+            seed: self.seed,
             current_depth: self.current_depth,
-            minerals: self.minerals,
+            nutrients: self.nutrients,
+            upgrade_levels: self.upgrade_levels.clone(),
+            water: self.water,
+            drilling: self.drilling,
+            stats: self.stats.clone(),
+            game_over: self.game_over,
             tiles: mem::take(&mut self.client_view_update.tiles),
             roots: mem::take(&mut self.client_view_update.roots),
-            attractors: mem::take(&mut self.client_view_update.attractors),
+            attractors: self.tree_roots.attractors.clone(),
         }
     }
 }
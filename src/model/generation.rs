@@ -18,22 +18,55 @@ impl Model {
     }
 
     pub fn generate_area(&mut self, area: Area) {
-        for y in area.y_min..=area.y_max {
-            for x in area.x_min..=area.x_max {
-                let position = vec2(x, y);
-                let terrain_noise = self.noises[0].get(position.map(|x| x as f32));
-                let mineral_noise = self.noises[1].get(position.map(|x| x as f32));
-                let tile = self.tile_from_noise_value(mineral_noise, terrain_noise);
-                self.try_set_tile(position, tile);
-            }
-        }
+        let rules = &self.rules;
+        let noises = &self.noises;
+        let view_tiles = &mut self.client_view_update.tiles;
+        self.tiles.generate(area, |position| {
+            let sample = sample_biomes(&rules.biomes, rules.biome_blend, position.y as f32);
+            let (from, to) = (&rules.biomes[sample.from], &rules.biomes[sample.to]);
+            let layer_value = |biome: usize, name: &str| {
+                rules.biomes[biome]
+                    .noise_layers
+                    .iter()
+                    .position(|layer| layer.name == name)
+                    .map(|layer| noises[biome][layer].get(position.map(|x| x as f32)))
+            };
+            let noise = |name: &str| {
+                if sample.t == 0.0 {
+                    return layer_value(sample.from, name).unwrap_or(0.0);
+                }
+                match (layer_value(sample.from, name), layer_value(sample.to, name)) {
+                    (Some(from), Some(to)) => from + (to - from) * sample.t,
+                    (Some(value), None) | (None, Some(value)) => value,
+                    (None, None) => 0.0,
+                }
+            };
+            let tile = if sample.t == 0.0 {
+                tile_from_rules(&from.tiles, noise)
+            } else {
+                tile_from_rules(&lerp_tile_rules(&from.tiles, &to.tiles, sample.t), noise)
+            };
+            view_tiles.insert(position, ViewEvent::Changed(tile.clone()));
+            tile
+        });
+        self.generate_veins(area);
+        self.generate_structures(area);
     }
 
     pub fn remove_above(&mut self, depth: i32) {
-        self.tiles.retain(|pos, _| pos.y >= depth);
-        self.tree_roots
-            .roots
-            .retain(|_, root| root.position.y >= depth as f32);
+        self.tiles.remove_above(depth);
+        let grid = &mut self.tree_roots.grid;
+        let heads = &mut self.tree_roots.heads;
+        let merged_points = &mut self.merged_points;
+        self.tree_roots.roots.retain(|&id, root| {
+            let keep = root.position.y >= depth as f32;
+            if !keep {
+                grid.remove(id, root.segment());
+                heads.remove(&id);
+                merged_points.remove(&id);
+            }
+            keep
+        });
         self.tree_roots
             .attractors
             .retain(|attractor| attractor.position.y >= depth as f32);
@@ -45,27 +78,4 @@ impl Model {
             .insert(position, ViewEvent::Changed(tile.clone()));
         self.tiles.insert(position, tile);
     }
-
-    pub fn try_set_tile(&mut self, position: Position, tile: Tile) {
-        if !self.tiles.contains_key(&position) {
-            self.client_view_update
-                .tiles
-                .insert(position, ViewEvent::Changed(tile.clone()));
-            self.tiles.insert(position, tile);
-        }
-    }
-
-    fn tile_from_noise_value(&self, mineral_noise: f32, terrain_noise: f32) -> Tile {
-        if mineral_noise <= self.rules.mineral_frequency {
-            Tile::Mineral {
-                minerals: ((1.0 - mineral_noise / self.rules.mineral_frequency)
-                    * self.rules.mineral_richness)
-                    .clamp(0.5, self.rules.mineral_richness),
-            }
-        } else if terrain_noise <= self.rules.stone_frequency {
-            Tile::Stone
-        } else {
-            Tile::Dirt
-        }
-    }
 }
@@ -1,14 +1,9 @@
 use super::*;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Id(usize);
 
-impl Id {
-    pub fn raw(&self) -> usize {
-        self.0
-    }
-}
-
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IdGenerator {
     next_id: usize,
 }
@@ -1,135 +1,270 @@
 use super::*;
+use rand_chacha::ChaCha20Rng;
 
+mod attractors;
+mod biome;
 mod client_view;
 mod generation;
 mod id;
 mod multi_noise;
+mod nutrients;
+mod replay;
 mod root;
+mod root_grid;
 mod rules;
+mod save;
+mod split_patterns;
+mod stats;
+mod structures;
+mod tiles;
+mod upgrades;
+mod veins;
 
+pub use attractors::*;
+pub use biome::*;
 pub use client_view::*;
-use id::*;
+pub use id::*;
 use multi_noise::*;
-use root::*;
-use rules::*;
+pub use nutrients::*;
+pub use replay::*;
+pub use root::*;
+use root_grid::*;
+pub use rules::*;
+pub use split_patterns::*;
+pub use stats::*;
+pub use structures::*;
+pub use tiles::*;
+pub use upgrades::*;
 
 pub struct Model {
-    pub tiles: HashMap<Position, Tile>,
+    pub tiles: Tiles,
     pub tree_roots: TreeRoots,
     delta_time: f32,
     fixed_delta_time: f32,
     pub rules: Rules,
-    noises: [MultiNoise; 2],
+    /// Rules without upgrades applied, restored on every reset
+    pub base_rules: Rules,
+    seed: u64,
+    rng: ChaCha20Rng,
+    noise_seed: u32,
+    /// Noises for every layer of every biome
+    noises: Vec<Vec<MultiNoise>>,
     id_generator: IdGenerator,
-    pub minerals: f32,
+    pub nutrients: Nutrients,
+    upgrade_levels: HashMap<Upgrade, usize>,
+    pub water: f32,
     split_roots: bool,
+    /// Heads to split on the next tick
+    split_heads: Vec<Id>,
+    /// Points every head passed since its last node, merged into its current segment
+    merged_points: HashMap<Id, Vec<Vec2<f32>>>,
+    /// Whether heads drill through stone instead of stopping
+    pub drilling: bool,
     client_view_update: ClientView,
-    current_depth: f32,
+    pub current_depth: f32,
     generation_depth: i32,
+    pub stats: RunStats,
+    pub game_over: bool,
+    pub ticks: u64,
+    pub replay: Replay,
+    playback: Option<Playback>,
+    /// Whether the run was started from a replay, even after its messages run out
+    from_replay: bool,
 }
 
 type Position = Vec2<i32>;
 type Area = AABB<i32>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Tile {
     Dirt,
     Stone,
-    Mineral { minerals: f32 },
+    Clay,
+    Gravel,
+    Bedrock,
+    Toxic,
+    /// An empty cave pocket
+    Air,
+    Nitrogen {
+        amount: f32,
+    },
+    Phosphorus {
+        amount: f32,
+    },
+    Potassium {
+        amount: f32,
+    },
+    Water {
+        amount: f32,
+    },
+}
+
+impl Tile {
+    /// The consumable amount stored in the tile, if any.
+    pub fn resource_mut(&mut self) -> Option<&mut f32> {
+        match self {
+            Tile::Nitrogen { amount }
+            | Tile::Phosphorus { amount }
+            | Tile::Potassium { amount }
+            | Tile::Water { amount } => Some(amount),
+            _ => None,
+        }
+    }
+    pub fn resource(&self) -> Option<f32> {
+        self.clone().resource_mut().copied()
+    }
+    pub fn nutrient(&self) -> Option<Nutrient> {
+        match self {
+            Tile::Nitrogen { .. } => Some(Nutrient::Nitrogen),
+            Tile::Phosphorus { .. } => Some(Nutrient::Phosphorus),
+            Tile::Potassium { .. } => Some(Nutrient::Potassium),
+            _ => None,
+        }
+    }
 }
 
 impl Model {
-    pub fn new() -> Self {
-        let terrain_noise_properties = MultiNoiseProperties {
-            min_value: 0.0,
-            max_value: 1.0,
-            scale: 20.0,
-            octaves: 1,
-            lacunarity: 1.0,
-            persistance: 1.0,
-        };
+    pub fn new(rules: Rules) -> Self {
+        Self::with_seed(rules, global_rng().gen())
+    }
+    pub fn with_seed(rules: Rules, seed: u64) -> Self {
         let mut model = Self {
-            tiles: HashMap::new(),
+            tiles: Tiles::new(rules.chamber_width),
             tree_roots: TreeRoots::new(),
             fixed_delta_time: 1.0 / 20.0,
             delta_time: 0.0,
-            rules: Rules::default(),
-            noises: [
-                MultiNoise::new(global_rng().gen(), &terrain_noise_properties),
-                MultiNoise::new(
-                    global_rng().gen(),
-                    &MultiNoiseProperties {
-                        scale: 5.0,
-                        ..terrain_noise_properties
-                    },
-                ),
-            ],
+            rules: rules.clone(),
+            base_rules: rules.clone(),
+            seed,
+            rng: ChaCha20Rng::seed_from_u64(seed),
+            noise_seed: 0,
+            noises: Vec::new(),
             id_generator: IdGenerator::new(),
-            minerals: 0.0,
+            nutrients: Nutrients::default(),
+            upgrade_levels: HashMap::new(),
+            water: 0.0,
             split_roots: false,
-            client_view_update: ClientView::new(Rules::default()),
+            split_heads: Vec::new(),
+            merged_points: HashMap::new(),
+            drilling: true,
+            client_view_update: ClientView::new(rules.clone(), seed),
             current_depth: 0.0,
             generation_depth: 0,
+            stats: RunStats::default(),
+            game_over: false,
+            ticks: 0,
+            replay: Replay::new(seed, rules),
+            playback: None,
+            from_replay: false,
         };
-        model.reset();
+        model.reset_with_seed(seed);
         model
     }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn fixed_delta_time(&self) -> f32 {
+        self.fixed_delta_time
+    }
     pub fn reset(&mut self) {
-        self.tiles.clear();
+        self.reset_with_seed(global_rng().gen());
+    }
+    pub fn reset_with_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = ChaCha20Rng::seed_from_u64(seed);
+        self.rules = self.base_rules.clone();
+        self.tiles = Tiles::new(self.rules.chamber_width);
         self.tree_roots = TreeRoots::new();
-        for noise in &mut self.noises {
-            noise.set_seed(global_rng().gen());
-        }
+        self.noise_seed = self.rng.gen();
+        self.noises = biome_noises(&self.rules.biomes, self.noise_seed);
         self.id_generator = IdGenerator::new();
-        self.minerals = 10.0;
+        self.nutrients = Nutrients::uniform(10.0);
+        self.upgrade_levels = HashMap::new();
+        self.water = self.rules.initial_water;
         self.split_roots = true;
+        self.split_heads = Vec::new();
+        self.merged_points = HashMap::new();
+        self.drilling = true;
         self.generation_depth = 0;
         self.current_depth = 0.0;
-        self.client_view_update = ClientView::new(self.rules.clone());
+        self.stats = RunStats::default();
+        self.game_over = false;
+        self.ticks = 0;
+        self.replay = Replay::new(seed, self.rules.clone());
+        self.playback = None;
+        self.from_replay = false;
+        self.client_view_update = ClientView::new(self.rules.clone(), seed);
 
-        self.new_root(Root {
-            position: vec2(0.0, 0.0),
-            parent_root: None,
-            root_type: RootType::Head {
-                velocity: vec2(0.0, self.rules.root_growth_speed),
-            },
-        });
+        self.new_root(Root::new_head(
+            vec2(0.0, 0.0),
+            None,
+            vec2(0.0, self.rules.root_growth_speed),
+        ));
         self.fill_area(self.get_area(0, 20), Tile::Dirt);
         self.generate();
     }
     pub fn update(&mut self, delta_time: f32) {
+        if self.game_over {
+            return;
+        }
         self.delta_time += delta_time;
         if self.delta_time >= self.fixed_delta_time {
             self.delta_time -= self.fixed_delta_time;
-            self.update_roots();
+            self.tick();
+        }
+    }
+    pub fn tick(&mut self) {
+        self.apply_playback();
+        self.update_roots();
+        self.update_attractors();
 
-            self.current_depth = self
-                .tree_roots
-                .roots
-                .values()
-                .map(|root| root.position.y)
-                .max_by(|a, b| a.partial_cmp(b).unwrap())
-                .unwrap();
+        if let Some(depth) = self
+            .tree_roots
+            .roots
+            .values()
+            .map(|root| root.position.y)
+            .max_by(|a, b| a.partial_cmp(b).unwrap())
+        {
+            self.current_depth = depth;
+        }
 
-            self.client_view_update.current_depth = self.current_depth;
-            self.client_view_update.minerals = self.minerals;
+        self.generate();
+        self.ticks += 1;
+        self.check_game_over();
 
-            self.generate();
-        }
+        self.client_view_update.current_depth = self.current_depth;
+        self.client_view_update.nutrients = self.nutrients;
     }
-    pub fn handle_event(&mut self, event: &geng::Event) {}
+    pub fn handle_event(&mut self, _event: &geng::Event) {}
     pub fn handle_message(&mut self, message: Message) {
+        self.replay.messages.push(ReplayMessage {
+            tick: self.ticks,
+            message: message.clone(),
+        });
         match message {
-            Message::SpawnAttractor { pos } => {
-                if self.try_spend(self.rules.attractor_cost) {
-                    self.spawn_attractor(pos);
-                }
-            }
+            Message::SpawnAttractor { pos, kind } => self.spawn_attractor(pos, kind),
             Message::SplitRoot => {
-                if self.try_spend(self.rules.split_cost) {
+                if self.nutrients.try_spend(&self.rules.split_cost) {
                     self.split_roots = true;
                 }
             }
+            Message::SplitHead { id } => {
+                let is_head = matches!(
+                    self.tree_roots.roots.get(&id),
+                    Some(Root {
+                        root_type: RootType::Head { .. },
+                        ..
+                    })
+                );
+                if is_head
+                    && !self.split_heads.contains(&id)
+                    && self.nutrients.try_spend(&self.rules.split_head_cost)
+                {
+                    self.split_heads.push(id);
+                }
+            }
+            Message::BuyUpgrade { upgrade } => self.buy_upgrade(upgrade),
+            Message::ToggleDrilling => self.drilling = !self.drilling,
         }
     }
     fn generate(&mut self) {
@@ -142,11 +277,4 @@ impl Model {
             self.generation_depth += self.rules.generation_depth_max;
         }
     }
-    fn try_spend(&mut self, cost: f32) -> bool {
-        if self.minerals >= cost {
-            self.minerals -= cost;
-            return true;
-        }
-        false
-    }
 }
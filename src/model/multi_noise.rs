@@ -1,43 +1,80 @@
 use super::*;
-use noise::Seedable;
+use noise::{MultiFractal, Seedable};
 
 pub struct MultiNoise {
     noise: Box<dyn ::noise::NoiseFn<[f64; 2]> + Sync + Send>,
+    /// Octaves summed by `get`, the ridged multifractal does its own
+    octaves: usize,
+    /// Brings the output of the base function to roughly `-1..1`
+    amplitude: f32,
     properties: MultiNoiseProperties,
 }
 
+/// The base noise function that octaves are built from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NoiseAlgorithm {
+    OpenSimplex,
+    Perlin,
+    /// Cellular noise, every cell having a constant value
+    Worley,
+    Value,
+    /// Fractal noise with sharp ridges, useful for veins
+    RidgedMulti,
+}
+
 impl MultiNoise {
     pub fn new(seed: u32, properties: &MultiNoiseProperties) -> Self {
+        let noise: Box<dyn ::noise::NoiseFn<[f64; 2]> + Sync + Send> = match properties.algorithm {
+            NoiseAlgorithm::OpenSimplex => Box::new(::noise::OpenSimplex::new().set_seed(seed)),
+            // `noise` exports two structs named `Perlin`, a single octave of fBm is plain Perlin
+            NoiseAlgorithm::Perlin => Box::new(::noise::Fbm::new().set_seed(seed).set_octaves(1)),
+            NoiseAlgorithm::Worley => Box::new(::noise::Worley::new().set_seed(seed)),
+            NoiseAlgorithm::Value => Box::new(::noise::Value::new().set_seed(seed)),
+            NoiseAlgorithm::RidgedMulti => Box::new(
+                ::noise::RidgedMulti::new()
+                    .set_seed(seed)
+                    .set_octaves(properties.octaves)
+                    .set_lacunarity(properties.lacunarity as f64)
+                    .set_persistence(properties.persistance as f64),
+            ),
+        };
         Self {
-            noise: Box::new(::noise::OpenSimplex::new().set_seed(seed)),
+            noise,
+            octaves: match properties.algorithm {
+                NoiseAlgorithm::RidgedMulti => 1,
+                _ => properties.octaves,
+            },
+            amplitude: match properties.algorithm {
+                NoiseAlgorithm::OpenSimplex => 0.544,
+                _ => 1.0,
+            },
             properties: properties.clone(),
         }
     }
-    pub fn set_seed(&mut self, seed: u32) {
-        self.noise = Box::new(::noise::OpenSimplex::new().set_seed(seed));
-    }
     pub fn get(&self, pos: Vec2<f32>) -> f32 {
         let mut frequency = 1.0;
         let mut amplitude = 1.0;
         let mut value = 0.0;
-        for _ in 0..self.properties.octaves {
+        for _ in 0..self.octaves {
             value += self.noise.get([
                 pos.x as f64 / self.properties.scale as f64 * frequency as f64,
                 pos.y as f64 / self.properties.scale as f64 * frequency as f64,
             ]) as f32
-                / 0.544
+                / self.amplitude
                 * amplitude;
             frequency *= self.properties.lacunarity;
             amplitude *= self.properties.persistance;
         }
-        let value = value.max(-1.0).min(1.0);
+        let value = value.clamp(-1.0, 1.0);
         (value / 2.0 + 0.5) * (self.properties.max_value - self.properties.min_value)
             + self.properties.min_value
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MultiNoiseProperties {
+    pub algorithm: NoiseAlgorithm,
     pub min_value: f32,
     pub max_value: f32,
     pub scale: f32,
@@ -45,3 +82,17 @@ pub struct MultiNoiseProperties {
     pub lacunarity: f32,
     pub persistance: f32,
 }
+
+impl Default for MultiNoiseProperties {
+    fn default() -> Self {
+        Self {
+            algorithm: NoiseAlgorithm::OpenSimplex,
+            min_value: 0.0,
+            max_value: 1.0,
+            scale: 20.0,
+            octaves: 1,
+            lacunarity: 1.0,
+            persistance: 1.0,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Nutrient {
+    Nitrogen,
+    Phosphorus,
+    Potassium,
+}
+
+impl Nutrient {
+    pub const ALL: [Nutrient; 3] = [
+        Nutrient::Nitrogen,
+        Nutrient::Phosphorus,
+        Nutrient::Potassium,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Nutrient::Nitrogen => "Nitrogen",
+            Nutrient::Phosphorus => "Phosphorus",
+            Nutrient::Potassium => "Potassium",
+        }
+    }
+
+    /// A tile of this nutrient holding the given amount.
+    pub fn tile(self, amount: f32) -> Tile {
+        match self {
+            Nutrient::Nitrogen => Tile::Nitrogen { amount },
+            Nutrient::Phosphorus => Tile::Phosphorus { amount },
+            Nutrient::Potassium => Tile::Potassium { amount },
+        }
+    }
+}
+
+/// An amount of every nutrient, used both for stocks and costs.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Nutrients {
+    pub nitrogen: f32,
+    pub phosphorus: f32,
+    pub potassium: f32,
+}
+
+impl Nutrients {
+    pub fn uniform(amount: f32) -> Self {
+        Self {
+            nitrogen: amount,
+            phosphorus: amount,
+            potassium: amount,
+        }
+    }
+
+    pub fn scaled(&self, factor: f32) -> Self {
+        Self {
+            nitrogen: self.nitrogen * factor,
+            phosphorus: self.phosphorus * factor,
+            potassium: self.potassium * factor,
+        }
+    }
+
+    pub fn get(&self, nutrient: Nutrient) -> f32 {
+        match nutrient {
+            Nutrient::Nitrogen => self.nitrogen,
+            Nutrient::Phosphorus => self.phosphorus,
+            Nutrient::Potassium => self.potassium,
+        }
+    }
+
+    pub fn get_mut(&mut self, nutrient: Nutrient) -> &mut f32 {
+        match nutrient {
+            Nutrient::Nitrogen => &mut self.nitrogen,
+            Nutrient::Phosphorus => &mut self.phosphorus,
+            Nutrient::Potassium => &mut self.potassium,
+        }
+    }
+
+    pub fn covers(&self, cost: &Self) -> bool {
+        Nutrient::ALL
+            .iter()
+            .all(|&nutrient| self.get(nutrient) >= cost.get(nutrient))
+    }
+
+    /// Subtracts the cost if every nutrient covers it.
+    pub fn try_spend(&mut self, cost: &Self) -> bool {
+        if !self.covers(cost) {
+            return false;
+        }
+        for &nutrient in &Nutrient::ALL {
+            *self.get_mut(nutrient) -= cost.get(nutrient);
+        }
+        true
+    }
+}
@@ -0,0 +1,88 @@
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub rules: Rules,
+    pub messages: Vec<ReplayMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayMessage {
+    pub tick: u64,
+    pub message: Message,
+}
+
+impl Replay {
+    pub fn new(seed: u64, rules: Rules) -> Self {
+        Self {
+            seed,
+            rules,
+            messages: Vec::new(),
+        }
+    }
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
+        let writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+pub struct Playback {
+    replay: Replay,
+    next_message: usize,
+}
+
+impl Playback {
+    fn new(mut replay: Replay) -> Self {
+        replay.messages.sort_by_key(|message| message.tick);
+        Self {
+            replay,
+            next_message: 0,
+        }
+    }
+    fn take_messages(&mut self, tick: u64) -> Vec<Message> {
+        let mut messages = Vec::new();
+        while let Some(message) = self.replay.messages.get(self.next_message) {
+            if message.tick > tick {
+                break;
+            }
+            messages.push(message.message.clone());
+            self.next_message += 1;
+        }
+        messages
+    }
+    fn is_finished(&self) -> bool {
+        self.next_message >= self.replay.messages.len()
+    }
+}
+
+impl Model {
+    pub fn from_replay(replay: Replay) -> Self {
+        let mut model = Self::with_seed(replay.rules.clone(), replay.seed);
+        model.playback = Some(Playback::new(replay));
+        model.from_replay = true;
+        model
+    }
+    pub fn is_replaying(&self) -> bool {
+        self.playback.is_some()
+    }
+    pub fn is_from_replay(&self) -> bool {
+        self.from_replay
+    }
+    pub(super) fn apply_playback(&mut self) {
+        if let Some(playback) = &mut self.playback {
+            let messages = playback.take_messages(self.ticks);
+            if playback.is_finished() {
+                self.playback = None;
+            }
+            for message in messages {
+                self.handle_message(message);
+            }
+        }
+    }
+}
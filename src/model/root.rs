@@ -1,9 +1,15 @@
 use super::*;
+use std::collections::BTreeSet;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TreeRoots {
     pub roots: HashMap<Id, Root>,
     pub attractors: Vec<Attractor>,
+    #[serde(skip)]
+    pub grid: RootGrid,
+    /// Ids of the roots that are still growing, kept in order for deterministic searches
+    #[serde(skip)]
+    pub heads: BTreeSet<Id>,
 }
 
 impl TreeRoots {
@@ -11,170 +17,355 @@ impl TreeRoots {
         Self {
             roots: HashMap::new(),
             attractors: Vec::new(),
+            grid: RootGrid::new(),
+            heads: BTreeSet::new(),
         }
     }
+
+    pub fn find_heads(roots: &HashMap<Id, Root>) -> BTreeSet<Id> {
+        roots
+            .iter()
+            .filter(|(_, root)| root.is_head())
+            .map(|(&id, _)| id)
+            .collect()
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Root {
     pub position: Vec2<f32>,
     pub parent_root: Option<(Id, Vec2<f32>)>,
     pub root_type: RootType,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RootType {
     Node,
     Final,
-    Consumer { position: Position },
-    Head { velocity: Velocity },
-}
-
-#[derive(Debug, Clone)]
-pub struct Attractor {
-    pub position: Vec2<f32>,
-    root: Id,
+    Consumer {
+        position: Position,
+    },
+    Head {
+        velocity: Velocity,
+        /// Seconds spent eroding the stone the head is stuck in
+        drill_progress: f32,
+    },
 }
 
 type Velocity = Vec2<f32>;
 
+impl Root {
+    pub fn new_head(
+        position: Vec2<f32>,
+        parent_root: Option<(Id, Vec2<f32>)>,
+        velocity: Velocity,
+    ) -> Self {
+        Self {
+            position,
+            parent_root,
+            root_type: RootType::Head {
+                velocity,
+                drill_progress: 0.0,
+            },
+        }
+    }
+    pub fn is_head(&self) -> bool {
+        matches!(self.root_type, RootType::Head { .. })
+    }
+    pub fn segment(&self) -> Segment {
+        match self.parent_root {
+            Some((_, parent_pos)) => (parent_pos, self.position),
+            None => (self.position, self.position),
+        }
+    }
+}
+
 impl Model {
     pub fn update_roots(&mut self) {
-        let ids: Vec<Id> = self.tree_roots.roots.keys().copied().collect();
+        self.consume_deposits();
+        let mut ids: Vec<Id> = self.tree_roots.roots.keys().copied().collect();
+        ids.sort();
         for id in ids {
             if let Some(root) = self.tree_roots.roots.get(&id) {
                 let mut root = root.clone();
+                let old_segment = root.segment();
                 self.update_root(&mut root, id);
+                let new_segment = root.segment();
+                if new_segment != old_segment {
+                    self.tree_roots.grid.move_root(id, old_segment, new_segment);
+                }
+                if !root.is_head() {
+                    self.tree_roots.heads.remove(&id);
+                    self.merged_points.remove(&id);
+                }
                 *self.tree_roots.roots.get_mut(&id).unwrap() = root;
             }
         }
-        let roots = &self.tree_roots.roots;
-        if self.split_roots {
-            for attractor in &mut self.tree_roots.attractors {
-                if let Some(closest_id) = Self::closest_root_id(roots, attractor.position) {
-                    attractor.root = closest_id;
+        if self.split_roots || !self.split_heads.is_empty() {
+            let tree_roots = &mut self.tree_roots;
+            for index in 0..tree_roots.attractors.len() {
+                if tree_roots.attractors[index].root.is_none() {
+                    continue;
+                }
+                let position = tree_roots.attractors[index].position;
+                if let Some(closest_id) = Self::closest_root_id(tree_roots, position) {
+                    tree_roots.attractors[index].root = Some(closest_id);
                 }
             }
         }
         self.split_roots = false;
+        self.split_heads.clear();
     }
 
     fn update_root(&mut self, root: &mut Root, root_id: Id) {
-        match &mut root.root_type {
-            RootType::Head { velocity } => {
-                if let Some((index, attractor)) = self
-                    .tree_roots
-                    .attractors
-                    .iter()
-                    .enumerate()
-                    .find(|(_, attractor)| attractor.root == root_id)
-                {
-                    if attractor.position.y < root.position.y {
-                        self.tree_roots.attractors.remove(index);
-                    } else {
-                        let direction = (attractor.position - root.position).normalize();
-                        *velocity = (*velocity
-                            + direction / self.rules.root_inertia * self.fixed_delta_time)
-                            .clamp(self.rules.root_growth_speed);
-                    }
-                }
+        // Splits are only requested for one tick, so a drilling head splits instead of drilling
+        let split = self.split_roots || self.split_heads.contains(&root_id);
+        if !split && self.drill(root, root_id) {
+            return;
+        }
+        if let RootType::Head { velocity, .. } = &mut root.root_type {
+            let steering = self.attractor_steering(root.position, root_id);
+            if steering != vec2(0.0, 0.0) {
+                *velocity = (*velocity
+                    + steering / self.rules.root_inertia * self.fixed_delta_time)
+                    .clamp(self.rules.root_growth_speed);
+            }
 
-                if self.split_roots {
-                    self.split_root(root);
-                } else {
-                    let velocity = *velocity;
-                    self.grow_root(root, velocity);
+            let mut growth_factor = self.hydration();
+            match self.tiles.get(get_tile_pos(root.position)) {
+                Some(Tile::Clay) => {
+                    growth_factor *= self.rules.clay_growth_factor;
                 }
-                for (&other_id, other) in &self.tree_roots.roots {
-                    if other_id != root_id
-                        && Some(other_id) != root.parent_root.map(|(id, _)| id)
-                        && (other.position - root.position).len() <= self.rules.root_size
-                    {
-                        root.root_type = RootType::Final;
-                        break;
+                Some(Tile::Gravel) if self.rules.gravel_deflection > 0.0 => {
+                    let angle = self
+                        .rng
+                        .gen_range(-self.rules.gravel_deflection, self.rules.gravel_deflection);
+                    *velocity = Vec2::rotated(*velocity, angle);
+                }
+                Some(Tile::Toxic) => {
+                    for &nutrient in &Nutrient::ALL {
+                        let amount = self.nutrients.get_mut(nutrient);
+                        *amount =
+                            (*amount - self.rules.toxic_drain * self.fixed_delta_time).max(0.0);
                     }
                 }
-
-                if root.position.x.abs() > self.rules.chamber_width as f32 {
-                    root.root_type = RootType::Final;
+                Some(Tile::Air) => {
+                    growth_factor *= self.rules.air_growth_factor;
                 }
+                _ => (),
+            }
 
-                let position = get_tile_pos(root.position);
-                if let Some(tile) = self.tiles.get(&position) {
-                    match tile {
-                        Tile::Stone => {
-                            root.root_type = RootType::Final;
-                        }
-                        Tile::Mineral { .. } => {
-                            root.root_type = RootType::Consumer { position };
-                        }
-                        _ => (),
-                    }
-                }
+            self.water = (self.water - self.rules.water_usage * self.fixed_delta_time).max(0.0);
+            if split {
+                self.merged_points.remove(&root_id);
+                self.split_root(root);
+            } else {
+                let velocity = *velocity * growth_factor;
+                self.grow_root(root, root_id, velocity);
+            }
+            // Segments sharing the parent node always touch this one, so skip them
+            let parent_id = root.parent_root.map(|(id, _)| id);
+            let roots = &self.tree_roots.roots;
+            if self
+                .tree_roots
+                .grid
+                .query(root.position, self.rules.root_size)
+                .any(|other_id| {
+                    other_id != root_id
+                        && Some(other_id) != parent_id
+                        && roots[&other_id].parent_root.map(|(id, _)| id) != parent_id
+                })
+            {
+                root.root_type = RootType::Final;
+            }
 
-                self.client_view_update
-                    .roots
-                    .insert(root_id, ViewEvent::Changed(root.clone()));
+            if root.position.x.abs() > self.rules.chamber_width as f32 {
+                root.root_type = RootType::Final;
             }
-            RootType::Consumer { position } => {
-                let consume_limit = self.rules.mineral_consume_speed * self.fixed_delta_time;
-                let consumed = self.consume(&mut HashSet::new(), *position, consume_limit);
-                if consumed == 0.0 {
-                    root.root_type = RootType::Final;
+
+            let position = get_tile_pos(root.position);
+            if let Some(tile) = self.tiles.get(position) {
+                match tile {
+                    // Drilling heads stop in stone on the next update
+                    Tile::Stone if self.drilling => (),
+                    Tile::Stone | Tile::Bedrock => {
+                        root.root_type = RootType::Final;
+                    }
+                    tile if tile.resource().is_some() => {
+                        root.root_type = RootType::Consumer { position };
+                    }
+                    _ => (),
                 }
+            }
 
-                self.client_view_update
-                    .roots
-                    .insert(root_id, ViewEvent::Changed(root.clone()));
+            self.client_view_update
+                .roots
+                .insert(root_id, ViewEvent::Changed(root.clone()));
+        }
+    }
+
+    /// Erodes the stone a head is stuck in, paying for every second of drilling.
+    /// The head stops for good once drilling is turned off or can't be paid for.
+    /// Returns whether the head is stuck in stone.
+    fn drill(&mut self, root: &mut Root, root_id: Id) -> bool {
+        let position = get_tile_pos(root.position);
+        if !matches!(root.root_type, RootType::Head { .. })
+            || !matches!(self.tiles.get(position), Some(Tile::Stone))
+        {
+            return false;
+        }
+        let cost = self.rules.drill_cost.scaled(self.fixed_delta_time);
+        if !self.drilling || !self.nutrients.try_spend(&cost) {
+            root.root_type = RootType::Final;
+        } else if let RootType::Head { drill_progress, .. } = &mut root.root_type {
+            *drill_progress += self.fixed_delta_time;
+            if *drill_progress >= self.rules.drill_time {
+                *drill_progress = 0.0;
+                self.set_tile(position, Tile::Dirt);
             }
-            _ => (),
         }
+        self.client_view_update
+            .roots
+            .insert(root_id, ViewEvent::Changed(root.clone()));
+        true
     }
 
-    fn consume(
-        &mut self,
-        consumed_pos: &mut HashSet<Position>,
-        position: Position,
-        mut consume_limit: f32,
-    ) -> f32 {
-        if consumed_pos.contains(&position) {
-            return 0.0;
-        };
-        if let Some(tile) = self.tiles.get_mut(&position) {
-            if let Tile::Mineral { minerals } = tile {
-                let consume = minerals.min(consume_limit);
-                self.minerals += consume;
-                *minerals -= consume;
-                consume_limit -= consume;
-                consumed_pos.insert(position);
+    /// Drains the deposits under every consumer.
+    /// Each consumer takes from the nearest tiles first, but never more than its share
+    /// of a tile that several consumers reach, so that they split a deposit evenly.
+    fn consume_deposits(&mut self) {
+        let mut consumers: Vec<(Id, Position)> = self
+            .tree_roots
+            .roots
+            .iter()
+            .filter_map(|(&id, root)| match root.root_type {
+                RootType::Consumer { position } => Some((id, position)),
+                _ => None,
+            })
+            .collect();
+        consumers.sort_by_key(|&(id, _)| id);
+        let deposits: Vec<Vec<Position>> = consumers
+            .iter()
+            .map(|&(_, position)| self.get_deposit(position))
+            .collect();
 
+        let mut claims = HashMap::<Position, usize>::new();
+        for &position in deposits.iter().flatten() {
+            *claims.entry(position).or_default() += 1;
+        }
+        let shares: HashMap<Position, f32> = claims
+            .into_iter()
+            .map(|(position, count)| {
+                let amount = self
+                    .tiles
+                    .get(position)
+                    .and_then(Tile::resource)
+                    .unwrap_or(0.0);
+                (position, amount / count as f32)
+            })
+            .collect();
+
+        for ((id, position), deposit) in consumers.into_iter().zip(deposits) {
+            let consume_limit = match self.tiles.get(position) {
+                Some(Tile::Water { .. }) => self.rules.water_consume_speed,
+                Some(tile) if tile.nutrient().is_some() => self.rules.mineral_consume_speed,
+                _ => 0.0,
+            } * self.fixed_delta_time;
+            let mut consumed = 0.0;
+            for tile_position in deposit {
+                if consumed >= consume_limit {
+                    break;
+                }
+                let tile = self.tiles.get_mut(tile_position).unwrap();
+                let amount = tile.resource_mut().unwrap();
+                let consume = shares[&tile_position]
+                    .min(consume_limit - consumed)
+                    .min(*amount);
+                if consume <= 0.0 {
+                    continue;
+                }
+                *amount -= consume;
+                consumed += consume;
                 self.client_view_update
                     .tiles
-                    .insert(position, ViewEvent::Changed(tile.clone()));
+                    .insert(tile_position, ViewEvent::Changed(tile.clone()));
+            }
 
-                let consume_neighbours = if consume_limit > 0.0 {
-                    let mut consumed = 0.0;
-                    for neighbour in get_neighbours(position) {
-                        let consume = self.consume(consumed_pos, neighbour, consume_limit);
-                        consumed += consume;
-                        consume_limit -= consume;
-                        if consume_limit <= 0.0 {
-                            break;
-                        }
+            match self.tiles.get(position) {
+                Some(Tile::Water { .. }) => self.water += consumed,
+                Some(tile) => {
+                    if let Some(nutrient) = tile.nutrient() {
+                        *self.nutrients.get_mut(nutrient) += consumed;
+                        self.stats.minerals_gathered += consumed;
                     }
-                    consumed
-                } else {
-                    0.0
-                };
+                }
+                None => (),
+            }
+            if consumed == 0.0 {
+                let root = self.tree_roots.roots.get_mut(&id).unwrap();
+                root.root_type = RootType::Final;
+                self.client_view_update
+                    .roots
+                    .insert(id, ViewEvent::Changed(root.clone()));
+            }
+        }
+    }
 
-                return consume + consume_neighbours;
+    /// Tiles connected to the starting one through tiles of the same kind,
+    /// within `consume_radius` of it, ordered by distance.
+    fn get_deposit(&self, start: Position) -> Vec<Position> {
+        let kind = match self.tiles.get(start) {
+            Some(tile) if tile.resource().is_some() => mem::discriminant(tile),
+            _ => return Vec::new(),
+        };
+        let radius = self.rules.consume_radius;
+        let mut deposit = vec![start];
+        let mut visited: HashSet<Position> = deposit.iter().copied().collect();
+        let mut index = 0;
+        while let Some(&position) = deposit.get(index) {
+            index += 1;
+            for neighbour in get_neighbours(position) {
+                let delta = neighbour - start;
+                if delta.x.abs().max(delta.y.abs()) > radius || visited.contains(&neighbour) {
+                    continue;
+                }
+                if let Some(tile) = self.tiles.get(neighbour) {
+                    if mem::discriminant(tile) == kind {
+                        visited.insert(neighbour);
+                        deposit.push(neighbour);
+                    }
+                }
             }
         }
-        0.0
+        deposit
+    }
+
+    /// Growth speed multiplier, dropping when the tree runs out of water.
+    fn hydration(&self) -> f32 {
+        (self.water / self.rules.hydration_threshold)
+            .min(1.0)
+            .max(self.rules.dehydrated_growth_factor)
     }
 
-    fn grow_root(&mut self, root: &mut Root, velocity: Velocity) {
+    fn grow_root(&mut self, root: &mut Root, root_id: Id, velocity: Velocity) {
         let next_pos = root.position + velocity * self.fixed_delta_time;
+        if let Some((_, parent_pos)) = root.parent_root {
+            // Extend the current segment instead of placing a node
+            // while every point it covers stays close to it
+            let rules = &self.rules;
+            let merged_points = self.merged_points.entry(root_id).or_default();
+            merged_points.push(root.position);
+            if merged_points.len() <= rules.root_segment_max_points
+                && merged_points.iter().all(|&point| {
+                    distance_to_segment(point, (parent_pos, next_pos))
+                        <= rules.root_compaction_tolerance
+                })
+            {
+                root.position = next_pos;
+                return;
+            }
+            merged_points.clear();
+        }
         let id = self.new_root(Root {
             position: root.position,
             parent_root: root.parent_root,
@@ -189,72 +380,79 @@ impl Model {
         self.client_view_update
             .roots
             .insert(id, ViewEvent::Changed(root.clone()));
+        self.tree_roots.grid.insert(id, root.segment());
+        if root.is_head() {
+            self.tree_roots.heads.insert(id);
+        }
         self.tree_roots.roots.insert(id, root);
         id
     }
 
-    pub fn spawn_attractor(&mut self, position: Vec2<f32>) {
-        if let Some(closest_id) = Self::closest_root_id(&self.tree_roots.roots, position) {
-            let attractor = Attractor {
-                position,
-                root: closest_id,
+    /// Finds the head closest to `position` among those above it.
+    /// Only a handful of heads grow at once, so scanning them beats a grid search,
+    /// which would have to walk every cell of the tree when no head qualifies.
+    pub(super) fn closest_root_id(tree_roots: &TreeRoots, position: Vec2<f32>) -> Option<Id> {
+        let mut closest: Option<(Id, f32)> = None;
+        for &id in &tree_roots.heads {
+            let root = &tree_roots.roots[&id];
+            if root.position.y >= position.y {
+                continue;
+            }
+            let distance = (root.position - position).len();
+            let closer = match closest {
+                Some((_, closest_distance)) => distance < closest_distance,
+                None => true,
             };
-            self.client_view_update
-                .attractors
-                .push(ViewEvent::Changed(attractor.clone()));
-            self.tree_roots.attractors.push(attractor);
+            if closer {
+                closest = Some((id, distance));
+            }
         }
-    }
-
-    fn closest_root_id(roots: &HashMap<Id, Root>, position: Vec2<f32>) -> Option<Id> {
-        roots
-            .iter()
-            .filter_map(|(id, root)| {
-                if let RootType::Head { .. } = root.root_type {
-                    if root.position.y < position.y {
-                        let distance = (root.position - position).len();
-                        return Some((id, distance));
-                    }
-                }
-                None
-            })
-            .min_by(|(_, da), (_, db)| da.partial_cmp(&db).unwrap())
-            .map(|(&id, _)| id)
+        closest.map(|(id, _)| id)
     }
 
     pub fn split_root(&mut self, root: &mut Root) {
-        let left_dir = get_random_dir(f32::PI * 2.0 / 3.0, f32::PI * 5.0 / 6.0);
-        let left_pos =
-            root.position + left_dir * self.fixed_delta_time * self.rules.root_growth_speed;
-        let right_dir = get_random_dir(f32::PI / 6.0, f32::PI / 3.0);
-        let right_pos =
-            root.position + right_dir * self.fixed_delta_time * self.rules.root_growth_speed;
+        let pattern = self.rules.split_pattern();
+        if pattern.children == 0 {
+            return;
+        }
+        let direction = match root.root_type {
+            RootType::Head { velocity, .. } if velocity.len() > 0.0 => velocity.normalize(),
+            _ => vec2(0.0, 1.0),
+        };
+        let mut velocities = Vec::with_capacity(pattern.children);
+        for angle in pattern.angles() {
+            let jitter = if pattern.jitter > 0.0 {
+                self.rng.gen_range(-pattern.jitter, pattern.jitter)
+            } else {
+                0.0
+            };
+            velocities
+                .push(Vec2::rotated(direction, angle + jitter) * self.rules.root_growth_speed);
+        }
+
         let id = self.new_root(Root {
             position: root.position,
             parent_root: root.parent_root,
             root_type: RootType::Node,
         });
-        self.new_root(Root {
-            position: left_pos,
-            parent_root: Some((id, root.position)),
-            root_type: RootType::Head {
-                velocity: left_dir * self.rules.root_growth_speed,
-            },
-        });
-        root.parent_root = Some((id, root.position));
-        root.position = right_pos;
-        root.root_type = RootType::Head {
-            velocity: right_dir * self.rules.root_growth_speed,
-        };
+        self.stats.branches_grown += pattern.children - 1;
+        // The split head becomes the last child, keeping its id
+        let last = velocities.pop().unwrap();
+        for velocity in velocities {
+            self.new_root(Root::new_head(
+                root.position + velocity * self.fixed_delta_time,
+                Some((id, root.position)),
+                velocity,
+            ));
+        }
+        *root = Root::new_head(
+            root.position + last * self.fixed_delta_time,
+            Some((id, root.position)),
+            last,
+        );
     }
 }
 
-fn get_random_dir(min_angle: f32, max_angle: f32) -> Velocity {
-    let angle = global_rng().gen_range(min_angle, max_angle);
-    let (y, x) = angle.sin_cos();
-    vec2(x, y)
-}
-
 fn get_tile_pos(pos: Vec2<f32>) -> Position {
     pos.map(|x| x.floor() as i32)
 }
@@ -262,7 +460,7 @@ fn get_tile_pos(pos: Vec2<f32>) -> Position {
 fn get_neighbours(pos: Position) -> impl Iterator<Item = Position> {
     ((pos.x - 1)..=(pos.x + 1)).flat_map(move |x| {
         ((pos.y - 1)..=(pos.y + 1))
-            .filter(move |&y| y != 0 || x != 0)
+            .filter(move |&y| y != pos.y || x != pos.x)
             .map(move |y| vec2(x, y))
     })
 }
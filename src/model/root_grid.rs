@@ -0,0 +1,90 @@
+use super::*;
+
+const CELL_SIZE: f32 = 1.0;
+
+/// Spatial hash over root segments, so that proximity queries
+/// only look at nearby roots instead of the whole tree.
+/// Each root is stored in every cell its segment (from the parent to the root) touches.
+#[derive(Debug, Clone, Default)]
+pub struct RootGrid {
+    cells: HashMap<Position, Vec<(Id, Segment)>>,
+}
+
+pub type Segment = (Vec2<f32>, Vec2<f32>);
+
+impl RootGrid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_roots(roots: &HashMap<Id, Root>) -> Self {
+        let mut grid = Self::new();
+        for (&id, root) in roots {
+            grid.insert(id, root.segment());
+        }
+        grid
+    }
+
+    pub fn insert(&mut self, id: Id, segment: Segment) {
+        let (min, max) = get_cells(segment);
+        for cell in iter_cells(min, max) {
+            self.cells.entry(cell).or_default().push((id, segment));
+        }
+    }
+
+    pub fn remove(&mut self, id: Id, segment: Segment) {
+        let (min, max) = get_cells(segment);
+        for cell in iter_cells(min, max) {
+            if let Some(roots) = self.cells.get_mut(&cell) {
+                roots.retain(|&(other_id, _)| other_id != id);
+                if roots.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    pub fn move_root(&mut self, id: Id, old_segment: Segment, new_segment: Segment) {
+        self.remove(id, old_segment);
+        self.insert(id, new_segment);
+    }
+
+    /// Iterates over all roots whose segment passes within `radius` of `position`.
+    /// A root may be yielded more than once if its segment spans several cells.
+    pub fn query(&self, position: Vec2<f32>, radius: f32) -> impl Iterator<Item = Id> + '_ {
+        let min = get_cell(position - vec2(radius, radius));
+        let max = get_cell(position + vec2(radius, radius));
+        iter_cells(min, max)
+            .filter_map(move |cell| self.cells.get(&cell))
+            .flatten()
+            .filter(move |&&(_, segment)| distance_to_segment(position, segment) <= radius)
+            .map(|&(id, _)| id)
+    }
+}
+
+fn get_cell(position: Vec2<f32>) -> Position {
+    position.map(|x| (x / CELL_SIZE).floor() as i32)
+}
+
+fn get_cells((start, end): Segment) -> (Position, Position) {
+    let start = get_cell(start);
+    let end = get_cell(end);
+    (
+        vec2(start.x.min(end.x), start.y.min(end.y)),
+        vec2(start.x.max(end.x), start.y.max(end.y)),
+    )
+}
+
+fn iter_cells(min: Position, max: Position) -> impl Iterator<Item = Position> {
+    (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| vec2(x, y)))
+}
+
+pub fn distance_to_segment(point: Vec2<f32>, (start, end): Segment) -> f32 {
+    let delta = end - start;
+    let length = delta.len();
+    if length == 0.0 {
+        return (point - start).len();
+    }
+    let t = (Vec2::dot(point - start, delta) / (length * length)).clamp(0.0, 1.0);
+    (point - (start + delta * t)).len()
+}
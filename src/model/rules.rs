@@ -1,18 +1,56 @@
-#[derive(Debug, Clone)]
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Rules {
     pub root_growth_speed: f32,
     pub chamber_width: usize,
-    pub stone_frequency: f32,
+    pub biomes: Vec<Biome>,
+    /// Depth range over which neighbouring biomes are mixed
+    pub biome_blend: f32,
+    pub structures: Vec<Structure>,
+    /// Depth from which mineral veins appear
+    pub vein_depth_start: i32,
+    /// Chance for a vein to start on a generated row
+    pub vein_chance: f32,
+    pub vein_length: usize,
+    /// Minerals at the start of a vein, dropping along its length
+    pub vein_richness: f32,
+    /// Chance for a vein to branch on every step
+    pub vein_branch_chance: f32,
+    pub clay_growth_factor: f32,
+    pub air_growth_factor: f32,
+    /// Maximum angle (in radians) a head is turned by per tick in gravel
+    pub gravel_deflection: f32,
+    /// Nutrients of every kind lost per second for every head inside a toxic tile
+    pub toxic_drain: f32,
     pub root_inertia: f32,
-    pub mineral_frequency: f32,
-    pub mineral_richness: f32,
     pub mineral_consume_speed: f32,
-    pub split_cost: f32,
-    pub attractor_cost: f32,
+    pub water_consume_speed: f32,
+    /// How far from a consumer its deposit is drained
+    pub consume_radius: i32,
+    pub initial_water: f32,
+    pub water_usage: f32,
+    pub hydration_threshold: f32,
+    pub dehydrated_growth_factor: f32,
+    pub split_cost: Nutrients,
+    pub split_patterns: Vec<SplitPattern>,
+    /// Name of the split pattern in use
+    pub split_pattern: String,
+    /// Cost of splitting a single head
+    pub split_head_cost: Nutrients,
+    /// Nutrients spent per second while a head drills through stone
+    pub drill_cost: Nutrients,
+    /// Seconds needed to turn a stone tile into dirt
+    pub drill_time: f32,
+    pub upgrades: Vec<UpgradeRules>,
+    pub attractors: Vec<AttractorRules>,
     pub generation_depth_max: i32,
     pub generation_depth_min: i32,
     pub deletion_depth: i32,
     pub root_size: f32,
+    pub root_compaction_tolerance: f32,
+    pub root_segment_max_points: usize,
 }
 
 impl Default for Rules {
@@ -20,17 +58,110 @@ impl Default for Rules {
         Self {
             root_growth_speed: 2.0,
             chamber_width: 51,
-            stone_frequency: 0.2,
+            biomes: default_biomes(),
+            biome_blend: 20.0,
+            structures: default_structures(),
+            vein_depth_start: 30,
+            vein_chance: 0.03,
+            vein_length: 40,
+            vein_richness: 4.0,
+            vein_branch_chance: 0.05,
+            clay_growth_factor: 0.5,
+            air_growth_factor: 2.0,
+            gravel_deflection: 0.3,
+            toxic_drain: 1.0,
             root_inertia: 1.0,
-            mineral_frequency: 0.05,
-            mineral_richness: 2.0,
             mineral_consume_speed: 1.0,
-            split_cost: 1.0,
-            attractor_cost: 2.0,
+            water_consume_speed: 2.0,
+            consume_radius: 16,
+            initial_water: 10.0,
+            water_usage: 0.1,
+            hydration_threshold: 5.0,
+            dehydrated_growth_factor: 0.25,
+            split_cost: Nutrients {
+                nitrogen: 1.0,
+                ..default()
+            },
+            attractors: default_attractors(),
+            split_patterns: default_split_patterns(),
+            split_pattern: "fork".to_owned(),
+            split_head_cost: Nutrients {
+                nitrogen: 0.5,
+                ..default()
+            },
+            upgrades: default_upgrades(),
+            drill_cost: Nutrients {
+                potassium: 0.5,
+                ..default()
+            },
+            drill_time: 2.0,
             generation_depth_max: 200,
             generation_depth_min: 100,
             deletion_depth: 20,
             root_size: 0.1,
+            root_compaction_tolerance: 0.02,
+            root_segment_max_points: 32,
+        }
+    }
+}
+
+impl Rules {
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        let mut rules: Self = serde_json::from_reader(reader)?;
+        if rules.biomes.is_empty() {
+            rules.biomes.push(Biome::default());
+        }
+        rules.biomes.sort_by_key(|biome| biome.depth_start);
+        for biome in &rules.biomes {
+            biome.validate()?;
+        }
+        for pattern in &rules.split_patterns {
+            pattern.validate()?;
+        }
+        Ok(rules)
+    }
+}
+
+/// Reloads rules from a config file whenever its modification time changes.
+pub struct RulesWatcher {
+    path: std::path::PathBuf,
+    modified: Option<std::time::SystemTime>,
+}
+
+impl RulesWatcher {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            modified: None,
+        }
+    }
+    /// Returns the new rules if the file has changed since the last poll.
+    pub fn poll(&mut self) -> Option<Rules> {
+        let modified = match std::fs::metadata(&self.path).and_then(|meta| meta.modified()) {
+            Ok(modified) => modified,
+            Err(e) => {
+                if self.modified.is_none() {
+                    warn!("Failed to read {:?}, using default rules: {}", self.path, e);
+                    self.modified = Some(std::time::UNIX_EPOCH);
+                    return Some(Rules::default());
+                }
+                return None;
+            }
+        };
+        if self.modified == Some(modified) {
+            return None;
+        }
+        self.modified = Some(modified);
+        match Rules::load(&self.path) {
+            Ok(rules) => {
+                info!("Loaded rules from {:?}", self.path);
+                Some(rules)
+            }
+            Err(e) => {
+                error!("Failed to load rules from {:?}: {}", self.path, e);
+                None
+            }
         }
     }
 }
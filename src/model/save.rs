@@ -0,0 +1,123 @@
+use super::*;
+
+const SAVE_VERSION: u32 = 20;
+
+#[derive(Serialize, Deserialize)]
+struct SaveState {
+    seed: u64,
+    rng_word_pos: u128,
+    rules: Rules,
+    base_rules: Rules,
+    noise_seed: u32,
+    tiles: Vec<(Position, Tile)>,
+    tree_roots: TreeRoots,
+    id_generator: IdGenerator,
+    nutrients: Nutrients,
+    upgrade_levels: HashMap<Upgrade, usize>,
+    water: f32,
+    split_roots: bool,
+    split_heads: Vec<Id>,
+    merged_points: HashMap<Id, Vec<Vec2<f32>>>,
+    drilling: bool,
+    current_depth: f32,
+    generation_depth: i32,
+    stats: RunStats,
+    game_over: bool,
+    ticks: u64,
+    replay: Replay,
+    from_replay: bool,
+}
+
+impl Model {
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
+        let state = SaveState {
+            seed: self.seed,
+            rng_word_pos: self.rng.get_word_pos(),
+            rules: self.rules.clone(),
+            base_rules: self.base_rules.clone(),
+            noise_seed: self.noise_seed,
+            tiles: self
+                .tiles
+                .iter()
+                .map(|(pos, tile)| (pos, tile.clone()))
+                .collect(),
+            tree_roots: self.tree_roots.clone(),
+            id_generator: self.id_generator.clone(),
+            nutrients: self.nutrients,
+            upgrade_levels: self.upgrade_levels.clone(),
+            water: self.water,
+            split_roots: self.split_roots,
+            split_heads: self.split_heads.clone(),
+            merged_points: self.merged_points.clone(),
+            drilling: self.drilling,
+            current_depth: self.current_depth,
+            generation_depth: self.generation_depth,
+            stats: self.stats.clone(),
+            game_over: self.game_over,
+            ticks: self.ticks,
+            replay: self.replay.clone(),
+            from_replay: self.from_replay,
+        };
+
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        bincode::serialize_into(&mut writer, &SAVE_VERSION)?;
+        bincode::serialize_into(&mut writer, &state)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        let version: u32 = bincode::deserialize_from(&mut reader)?;
+        if version != SAVE_VERSION {
+            anyhow::bail!(
+                "Unsupported save version {} (expected {})",
+                version,
+                SAVE_VERSION
+            );
+        }
+        let state: SaveState = bincode::deserialize_from(&mut reader)?;
+
+        let noises = biome_noises(&state.rules.biomes, state.noise_seed);
+        let mut tree_roots = state.tree_roots;
+        tree_roots.grid = RootGrid::from_roots(&tree_roots.roots);
+        tree_roots.heads = TreeRoots::find_heads(&tree_roots.roots);
+        let mut tiles = Tiles::new(state.rules.chamber_width);
+        for (pos, tile) in state.tiles {
+            tiles.insert(pos, tile);
+        }
+        Ok(Self {
+            tiles,
+            tree_roots,
+            fixed_delta_time: 1.0 / 20.0,
+            delta_time: 0.0,
+            client_view_update: ClientView::new(state.rules.clone(), state.seed),
+            rules: state.rules,
+            base_rules: state.base_rules,
+            seed: state.seed,
+            rng: {
+                let mut rng = ChaCha20Rng::seed_from_u64(state.seed);
+                rng.set_word_pos(state.rng_word_pos);
+                rng
+            },
+            noise_seed: state.noise_seed,
+            noises,
+            id_generator: state.id_generator,
+            nutrients: state.nutrients,
+            upgrade_levels: state.upgrade_levels,
+            water: state.water,
+            split_roots: state.split_roots,
+            split_heads: state.split_heads,
+            merged_points: state.merged_points,
+            drilling: state.drilling,
+            current_depth: state.current_depth,
+            generation_depth: state.generation_depth,
+            stats: state.stats,
+            game_over: state.game_over,
+            ticks: state.ticks,
+            replay: state.replay,
+            playback: None,
+            from_replay: state.from_replay,
+        })
+    }
+}
@@ -0,0 +1,62 @@
+use super::*;
+
+/// How a head branches when it splits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitPattern {
+    pub name: String,
+    pub children: usize,
+    /// Angle (in radians) between the outermost children,
+    /// centered on the direction the head was growing in
+    pub spread: f32,
+    /// Maximum random turn (in radians) added to every child
+    pub jitter: f32,
+}
+
+impl SplitPattern {
+    pub fn new(name: &str, children: usize, spread: f32, jitter: f32) -> Self {
+        Self {
+            name: name.to_owned(),
+            children,
+            spread,
+            jitter,
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.children == 0 {
+            anyhow::bail!("Split pattern {:?} has no children", self.name);
+        }
+        Ok(())
+    }
+
+    /// Angles of the children relative to the growth direction, before jitter.
+    pub fn angles(&self) -> impl Iterator<Item = f32> + '_ {
+        (0..self.children).map(move |index| {
+            if self.children == 1 {
+                0.0
+            } else {
+                self.spread * (index as f32 / (self.children - 1) as f32 - 0.5)
+            }
+        })
+    }
+}
+
+pub fn default_split_patterns() -> Vec<SplitPattern> {
+    vec![
+        SplitPattern::new("fork", 2, f32::PI / 2.0, f32::PI / 12.0),
+        SplitPattern::new("trident", 3, f32::PI * 2.0 / 3.0, f32::PI / 18.0),
+        SplitPattern::new("fan", 5, f32::PI, f32::PI / 24.0),
+    ]
+}
+
+impl Rules {
+    /// The selected split pattern, falling back to the first one or a plain fork.
+    pub fn split_pattern(&self) -> SplitPattern {
+        self.split_patterns
+            .iter()
+            .find(|pattern| pattern.name == self.split_pattern)
+            .or_else(|| self.split_patterns.first())
+            .cloned()
+            .unwrap_or_else(|| SplitPattern::new("fork", 2, f32::PI / 2.0, 0.0))
+    }
+}
@@ -0,0 +1,23 @@
+use super::*;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunStats {
+    pub minerals_gathered: f32,
+    pub branches_grown: usize,
+}
+
+impl Model {
+    /// The run is over once no root can grow or consume anymore.
+    /// Splitting only affects heads, so leftover nutrients can't revive it.
+    pub(super) fn check_game_over(&mut self) {
+        let active = self.tree_roots.roots.values().any(|root| {
+            matches!(
+                root.root_type,
+                RootType::Head { .. } | RootType::Consumer { .. }
+            )
+        });
+        if !active {
+            self.game_over = true;
+        }
+    }
+}
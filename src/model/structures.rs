@@ -0,0 +1,135 @@
+use super::*;
+
+/// A hand-authored pattern of tiles stamped into newly generated terrain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Structure {
+    pub name: String,
+    /// Chance for the structure to start on a generated row, by depth.
+    /// Each entry lasts until the next one, the structure never appears above the first one.
+    pub rarity: Vec<StructureRarity>,
+    /// Tiles placed for each character of the pattern.
+    /// Characters missing from the legend keep the generated tile.
+    pub legend: HashMap<char, Tile>,
+    /// Rows of the pattern from top to bottom
+    pub pattern: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructureRarity {
+    pub depth_start: i32,
+    pub chance: f32,
+}
+
+impl Structure {
+    pub fn chance(&self, depth: i32) -> f32 {
+        self.rarity
+            .iter()
+            .filter(|rarity| rarity.depth_start <= depth)
+            .max_by_key(|rarity| rarity.depth_start)
+            .map_or(0.0, |rarity| rarity.chance)
+    }
+
+    pub fn width(&self) -> i32 {
+        self.pattern
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0) as i32
+    }
+}
+
+pub fn default_structures() -> Vec<Structure> {
+    let legend = |entries: &[(char, Tile)]| entries.iter().cloned().collect();
+    let pattern = |rows: &[&str]| rows.iter().map(|row| row.to_string()).collect();
+    vec![
+        Structure {
+            name: "Fossil".to_owned(),
+            rarity: vec![StructureRarity {
+                depth_start: 40,
+                chance: 0.01,
+            }],
+            legend: legend(&[('#', Tile::Stone), ('.', Tile::Clay)]),
+            pattern: pattern(&[
+                "..........",
+                ".#..#..#..",
+                ".########.",
+                ".#..#..#..",
+                "..........",
+            ]),
+        },
+        Structure {
+            name: "Geode".to_owned(),
+            rarity: vec![
+                StructureRarity {
+                    depth_start: 80,
+                    chance: 0.02,
+                },
+                StructureRarity {
+                    depth_start: 300,
+                    chance: 0.03,
+                },
+            ],
+            legend: legend(&[('#', Tile::Stone), ('K', Tile::Potassium { amount: 5.0 })]),
+            pattern: pattern(&[" ### ", "#KKK#", "#KKK#", "#KKK#", " ### "]),
+        },
+        Structure {
+            name: "Treasure chamber".to_owned(),
+            rarity: vec![StructureRarity {
+                depth_start: 250,
+                chance: 0.005,
+            }],
+            legend: legend(&[
+                ('B', Tile::Bedrock),
+                ('_', Tile::Air),
+                ('N', Tile::Nitrogen { amount: 10.0 }),
+                ('P', Tile::Phosphorus { amount: 10.0 }),
+                ('K', Tile::Potassium { amount: 10.0 }),
+            ]),
+            pattern: pattern(&[
+                "BBB___BBB",
+                "B_______B",
+                "B_NN_PP_B",
+                "B_NKKKP_B",
+                "BBBBBBBBB",
+            ]),
+        },
+    ]
+}
+
+impl Model {
+    /// Rolls every structure for each row of the area and stamps the ones that appear.
+    /// Structures may reach below the area, those tiles are then kept when it is generated.
+    pub(super) fn generate_structures(&mut self, area: Area) {
+        let half_width = self.rules.chamber_width as i32;
+        for y in area.y_min..=area.y_max {
+            for index in 0..self.rules.structures.len() {
+                let structure = &self.rules.structures[index];
+                let chance = structure.chance(y);
+                let width = structure.width();
+                if chance <= 0.0 || width > half_width * 2 + 1 {
+                    continue;
+                }
+                if self.rng.gen::<f32>() >= chance {
+                    continue;
+                }
+                let x = self.rng.gen_range(-half_width, half_width - width + 2);
+                self.stamp_structure(index, vec2(x, y));
+            }
+        }
+    }
+
+    fn stamp_structure(&mut self, index: usize, corner: Position) {
+        let structure = &self.rules.structures[index];
+        let mut tiles = Vec::new();
+        for (dy, row) in structure.pattern.iter().enumerate() {
+            for (dx, symbol) in row.chars().enumerate() {
+                if let Some(tile) = structure.legend.get(&symbol) {
+                    tiles.push((corner + vec2(dx as i32, dy as i32), tile.clone()));
+                }
+            }
+        }
+        for (position, tile) in tiles {
+            self.set_tile(position, tile);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use super::*;
+use std::collections::VecDeque;
+
+const CHUNK_HEIGHT: i32 = 16;
+
+/// Tile storage split into fixed-height chunks of full-width rows along depth.
+/// Positions outside of the chamber width are never stored.
+#[derive(Debug, Clone)]
+pub struct Tiles {
+    half_width: i32,
+    first_chunk: i32,
+    chunks: VecDeque<Chunk>,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Chunk {
+    tiles: Vec<Option<Tile>>,
+}
+
+impl Chunk {
+    fn new(width: usize) -> Self {
+        Self {
+            tiles: vec![None; width * CHUNK_HEIGHT as usize],
+        }
+    }
+}
+
+impl Tiles {
+    pub fn new(chamber_width: usize) -> Self {
+        Self {
+            half_width: chamber_width as i32,
+            first_chunk: 0,
+            chunks: VecDeque::new(),
+            len: 0,
+        }
+    }
+
+    fn width(&self) -> usize {
+        (self.half_width * 2 + 1) as usize
+    }
+
+    fn chunk_index(y: i32) -> i32 {
+        y.div_euclid(CHUNK_HEIGHT)
+    }
+
+    fn tile_index(&self, position: Position) -> Option<(i32, usize)> {
+        if position.x.abs() > self.half_width {
+            return None;
+        }
+        let row = position.y.rem_euclid(CHUNK_HEIGHT) as usize;
+        let column = (position.x + self.half_width) as usize;
+        Some((Self::chunk_index(position.y), row * self.width() + column))
+    }
+
+    fn chunk(&self, chunk_index: i32) -> Option<&Chunk> {
+        if chunk_index < self.first_chunk {
+            return None;
+        }
+        self.chunks.get((chunk_index - self.first_chunk) as usize)
+    }
+
+    fn chunk_mut(&mut self, chunk_index: i32) -> Option<&mut Chunk> {
+        if chunk_index < self.first_chunk {
+            return None;
+        }
+        self.chunks
+            .get_mut((chunk_index - self.first_chunk) as usize)
+    }
+
+    /// Returns the chunk, allocating it and any chunks in between if needed.
+    fn chunk_or_insert(&mut self, chunk_index: i32) -> &mut Chunk {
+        let width = self.width();
+        if self.chunks.is_empty() {
+            self.first_chunk = chunk_index;
+        }
+        while chunk_index < self.first_chunk {
+            self.chunks.push_front(Chunk::new(width));
+            self.first_chunk -= 1;
+        }
+        while chunk_index >= self.first_chunk + self.chunks.len() as i32 {
+            self.chunks.push_back(Chunk::new(width));
+        }
+        self.chunk_mut(chunk_index).unwrap()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn get(&self, position: Position) -> Option<&Tile> {
+        let (chunk_index, index) = self.tile_index(position)?;
+        self.chunk(chunk_index)?.tiles[index].as_ref()
+    }
+
+    pub fn get_mut(&mut self, position: Position) -> Option<&mut Tile> {
+        let (chunk_index, index) = self.tile_index(position)?;
+        self.chunk_mut(chunk_index)?.tiles[index].as_mut()
+    }
+
+    pub fn insert(&mut self, position: Position, tile: Tile) {
+        if let Some((chunk_index, index)) = self.tile_index(position) {
+            let slot = &mut self.chunk_or_insert(chunk_index).tiles[index];
+            let was_empty = slot.is_none();
+            *slot = Some(tile);
+            if was_empty {
+                self.len += 1;
+            }
+        }
+    }
+
+    /// Fills every empty tile of the area, one chunk at a time.
+    pub fn generate(&mut self, area: Area, mut generator: impl FnMut(Position) -> Tile) {
+        let min_x = area.x_min.max(-self.half_width);
+        let max_x = area.x_max.min(self.half_width);
+        let width = self.width();
+        let half_width = self.half_width;
+        for chunk_index in Self::chunk_index(area.y_min)..=Self::chunk_index(area.y_max) {
+            let y_start = (chunk_index * CHUNK_HEIGHT).max(area.y_min);
+            let y_end = (chunk_index * CHUNK_HEIGHT + CHUNK_HEIGHT - 1).min(area.y_max);
+            let chunk = self.chunk_or_insert(chunk_index);
+            let mut generated = 0;
+            for y in y_start..=y_end {
+                let row = y.rem_euclid(CHUNK_HEIGHT) as usize * width;
+                for x in min_x..=max_x {
+                    let slot = &mut chunk.tiles[row + (x + half_width) as usize];
+                    if slot.is_none() {
+                        *slot = Some(generator(vec2(x, y)));
+                        generated += 1;
+                    }
+                }
+            }
+            self.len += generated;
+        }
+    }
+
+    /// Removes every tile above the given depth, dropping whole chunks where possible.
+    pub fn remove_above(&mut self, depth: i32) {
+        let depth_chunk = Self::chunk_index(depth);
+        while self.first_chunk < depth_chunk {
+            match self.chunks.pop_front() {
+                Some(chunk) => {
+                    self.len -= chunk.tiles.iter().filter(|tile| tile.is_some()).count();
+                    self.first_chunk += 1;
+                }
+                None => {
+                    self.first_chunk = depth_chunk;
+                    break;
+                }
+            }
+        }
+        let rows = depth.rem_euclid(CHUNK_HEIGHT) as usize;
+        let width = self.width();
+        if let Some(chunk) = self.chunk_mut(depth_chunk) {
+            let mut removed = 0;
+            for slot in &mut chunk.tiles[..rows * width] {
+                if slot.take().is_some() {
+                    removed += 1;
+                }
+            }
+            self.len -= removed;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Position, &Tile)> {
+        let width = self.width();
+        let half_width = self.half_width;
+        let first_chunk = self.first_chunk;
+        self.chunks
+            .iter()
+            .enumerate()
+            .flat_map(move |(offset, chunk)| {
+                let y_start = (first_chunk + offset as i32) * CHUNK_HEIGHT;
+                chunk
+                    .tiles
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(index, tile)| {
+                        let position = vec2(
+                            (index % width) as i32 - half_width,
+                            y_start + (index / width) as i32,
+                        );
+                        tile.as_ref().map(|tile| (position, tile))
+                    })
+            })
+    }
+
+    /// Iterates over the stored tiles of the area, only visiting the chunks and rows it overlaps.
+    pub fn iter_area(&self, area: Area) -> impl Iterator<Item = (Position, &Tile)> {
+        let min_x = area.x_min.max(-self.half_width);
+        let max_x = area.x_max.min(self.half_width);
+        let width = self.width();
+        let half_width = self.half_width;
+        let first_chunk = Self::chunk_index(area.y_min).max(self.first_chunk);
+        let last_chunk =
+            Self::chunk_index(area.y_max).min(self.first_chunk + self.chunks.len() as i32 - 1);
+        (first_chunk..=last_chunk)
+            .filter_map(move |chunk_index| {
+                self.chunk(chunk_index).map(|chunk| (chunk_index, chunk))
+            })
+            .flat_map(move |(chunk_index, chunk)| {
+                let y_start = (chunk_index * CHUNK_HEIGHT).max(area.y_min);
+                let y_end = (chunk_index * CHUNK_HEIGHT + CHUNK_HEIGHT - 1).min(area.y_max);
+                (y_start..=y_end).flat_map(move |y| {
+                    let row = y.rem_euclid(CHUNK_HEIGHT) as usize * width;
+                    (min_x..=max_x).filter_map(move |x| {
+                        chunk.tiles[row + (x + half_width) as usize]
+                            .as_ref()
+                            .map(|tile| (vec2(x, y), tile))
+                    })
+                })
+            })
+    }
+}
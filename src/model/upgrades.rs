@@ -0,0 +1,132 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Upgrade {
+    GrowthSpeed,
+    ConsumeSpeed,
+    Steering,
+    WaterEfficiency,
+}
+
+impl Upgrade {
+    pub const ALL: [Upgrade; 4] = [
+        Upgrade::GrowthSpeed,
+        Upgrade::ConsumeSpeed,
+        Upgrade::Steering,
+        Upgrade::WaterEfficiency,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Upgrade::GrowthSpeed => "Growth speed",
+            Upgrade::ConsumeSpeed => "Consume speed",
+            Upgrade::Steering => "Steering",
+            Upgrade::WaterEfficiency => "Water efficiency",
+        }
+    }
+}
+
+/// A tiered boost to one of the rules, bought with nutrients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeRules {
+    pub upgrade: Upgrade,
+    pub max_level: usize,
+    /// Cost of the first level
+    pub cost: Nutrients,
+    /// Cost multiplier for every next level
+    pub cost_growth: f32,
+    /// Multiplier applied to the upgraded rule with every level
+    pub factor: f32,
+}
+
+impl UpgradeRules {
+    /// Cost of buying the level after `level`.
+    pub fn cost(&self, level: usize) -> Nutrients {
+        self.cost.scaled(self.cost_growth.powi(level as i32))
+    }
+}
+
+pub fn default_upgrades() -> Vec<UpgradeRules> {
+    vec![
+        UpgradeRules {
+            upgrade: Upgrade::GrowthSpeed,
+            max_level: 5,
+            cost: Nutrients {
+                nitrogen: 5.0,
+                potassium: 5.0,
+                ..default()
+            },
+            cost_growth: 1.6,
+            factor: 1.15,
+        },
+        UpgradeRules {
+            upgrade: Upgrade::ConsumeSpeed,
+            max_level: 5,
+            cost: Nutrients {
+                phosphorus: 5.0,
+                potassium: 5.0,
+                ..default()
+            },
+            cost_growth: 1.6,
+            factor: 1.25,
+        },
+        UpgradeRules {
+            upgrade: Upgrade::Steering,
+            max_level: 3,
+            cost: Nutrients::uniform(4.0),
+            cost_growth: 2.0,
+            factor: 0.8,
+        },
+        UpgradeRules {
+            upgrade: Upgrade::WaterEfficiency,
+            max_level: 5,
+            cost: Nutrients {
+                potassium: 8.0,
+                ..default()
+            },
+            cost_growth: 1.5,
+            factor: 0.85,
+        },
+    ]
+}
+
+impl Model {
+    pub fn upgrade_level(&self, upgrade: Upgrade) -> usize {
+        self.upgrade_levels.get(&upgrade).copied().unwrap_or(0)
+    }
+
+    /// Buys the next level of the upgrade if it is affordable,
+    /// applying it to the rules for the rest of the run.
+    pub(super) fn buy_upgrade(&mut self, upgrade: Upgrade) {
+        let upgrade_rules = match self
+            .rules
+            .upgrades
+            .iter()
+            .find(|upgrade_rules| upgrade_rules.upgrade == upgrade)
+        {
+            Some(upgrade_rules) => upgrade_rules.clone(),
+            None => return,
+        };
+        let level = self.upgrade_level(upgrade);
+        if level >= upgrade_rules.max_level || !self.nutrients.try_spend(&upgrade_rules.cost(level))
+        {
+            return;
+        }
+        self.upgrade_levels.insert(upgrade, level + 1);
+
+        let factor = upgrade_rules.factor;
+        match upgrade {
+            Upgrade::GrowthSpeed => {
+                self.rules.root_growth_speed *= factor;
+                for root in self.tree_roots.roots.values_mut() {
+                    if let RootType::Head { velocity, .. } = &mut root.root_type {
+                        *velocity *= factor;
+                    }
+                }
+            }
+            Upgrade::ConsumeSpeed => self.rules.mineral_consume_speed *= factor,
+            Upgrade::Steering => self.rules.root_inertia *= factor,
+            Upgrade::WaterEfficiency => self.rules.water_usage *= factor,
+        }
+    }
+}
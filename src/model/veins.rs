@@ -0,0 +1,78 @@
+use super::*;
+
+const VEIN_MIN_RICHNESS: f32 = 0.5;
+/// Maximum turn (in radians) of a vein per step
+const VEIN_WANDER: f32 = 0.4;
+const VEIN_BRANCH_ANGLE: f32 = 0.8;
+
+struct VeinWalk {
+    position: Vec2<f32>,
+    angle: f32,
+    length: usize,
+    richness: f32,
+    nutrient: Nutrient,
+}
+
+impl Model {
+    /// Seeds veins on the rows of the area and random-walks them into thin, branching lines
+    /// of nutrients that get poorer towards their ends.
+    /// Veins may reach outside of the area, those tiles are then kept when it is generated.
+    pub(super) fn generate_veins(&mut self, area: Area) {
+        let half_width = self.rules.chamber_width as i32;
+        for y in area.y_min.max(self.rules.vein_depth_start)..=area.y_max {
+            if self.rng.gen::<f32>() >= self.rules.vein_chance {
+                continue;
+            }
+            let x = self.rng.gen_range(-half_width, half_width + 1);
+            let angle = self.rng.gen_range(0.0, f32::PI * 2.0);
+            let nutrient = Nutrient::ALL[self.rng.gen_range(0, Nutrient::ALL.len())];
+            self.walk_vein(VeinWalk {
+                position: vec2(x as f32, y as f32),
+                angle,
+                length: self.rules.vein_length,
+                richness: self.rules.vein_richness,
+                nutrient,
+            });
+        }
+    }
+
+    fn walk_vein(&mut self, walk: VeinWalk) {
+        let mut walks = vec![walk];
+        while let Some(mut walk) = walks.pop() {
+            let richness_step = (walk.richness - VEIN_MIN_RICHNESS) / walk.length.max(1) as f32;
+            for step in 0..walk.length {
+                let position = walk.position.map(|x| x.round() as i32);
+                if position.x.abs() > self.rules.chamber_width as i32 {
+                    break;
+                }
+                let replace = match self.tiles.get(position) {
+                    Some(Tile::Bedrock) | Some(Tile::Air) | Some(Tile::Water { .. }) => false,
+                    Some(tile) if tile.nutrient().is_some() => {
+                        tile.resource().unwrap() < walk.richness
+                    }
+                    _ => true,
+                };
+                if replace {
+                    self.set_tile(position, walk.nutrient.tile(walk.richness));
+                }
+
+                walk.angle += self.rng.gen_range(-VEIN_WANDER, VEIN_WANDER);
+                let (sin, cos) = walk.angle.sin_cos();
+                walk.position += vec2(cos, sin);
+                walk.richness -= richness_step;
+
+                let remaining = walk.length - step - 1;
+                if remaining > 1 && self.rng.gen::<f32>() < self.rules.vein_branch_chance {
+                    let side = if self.rng.gen() { 1.0 } else { -1.0 };
+                    walks.push(VeinWalk {
+                        position: walk.position,
+                        angle: walk.angle + side * VEIN_BRANCH_ANGLE,
+                        length: remaining / 2,
+                        richness: walk.richness,
+                        nutrient: walk.nutrient,
+                    });
+                }
+            }
+        }
+    }
+}
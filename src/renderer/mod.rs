@@ -13,14 +13,32 @@ pub struct Renderer {
     texture_buffer: usize,
     texture_size: Vec2<usize>,
     screen_size: Vec2<usize>,
+    show_high_scores: bool,
+    attractor_kind: model::AttractorKind,
+    /// Positions of the heads, for picking one to split
+    heads: HashMap<model::Id, Vec2<f32>>,
     pub request_view: bool,
 }
 
 const DEFAULT_SCREEN_SIZE: Vec2<usize> = Vec2 { x: 1024, y: 768 };
+/// How close to a head a click has to be to split only that head
+const SPLIT_HEAD_RADIUS: f32 = 2.0;
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Message {
     SplitRoot,
-    SpawnAttractor { pos: Vec2<f32> },
+    SplitHead {
+        id: model::Id,
+    },
+    SpawnAttractor {
+        pos: Vec2<f32>,
+        #[serde(default)]
+        kind: model::AttractorKind,
+    },
+    BuyUpgrade {
+        upgrade: model::Upgrade,
+    },
+    ToggleDrilling,
 }
 
 impl Renderer {
@@ -38,6 +56,9 @@ impl Renderer {
             texture_buffer: 4,
             texture_size: vec2(0, 0),
             screen_size: vec2(0, 0),
+            show_high_scores: false,
+            attractor_kind: model::AttractorKind::Weak,
+            heads: HashMap::new(),
             request_view: true,
         }
     }
@@ -46,6 +67,7 @@ impl Renderer {
         self.current_depth = 0.0;
         self.texture_offset = 0.0;
         self.request_view = true;
+        self.heads.clear();
         *texture = None;
     }
     fn scale(&self) -> f32 {
@@ -79,6 +101,7 @@ impl Renderer {
         &mut self,
         framebuffer: &mut ugli::Framebuffer,
         view: &model::ClientView,
+        high_scores: &HighScores,
         texture: &mut Option<ugli::Texture>,
     ) {
         ugli::clear(framebuffer, Some(Color::BLACK), None);
@@ -112,10 +135,66 @@ impl Renderer {
             Color::WHITE,
         );
 
-        let text = format!("Minerals: {}", view.minerals.floor());
+        self.draw_attractors(framebuffer, view);
+
+        let mut y = 20.0;
+        for &nutrient in model::Nutrient::ALL.iter().rev() {
+            let text = format!(
+                "{}: {}",
+                nutrient.name(),
+                view.nutrients.get(nutrient).floor()
+            );
+            self.geng
+                .default_font()
+                .draw(framebuffer, &text, vec2(20.0, y), 25.0, Color::WHITE);
+            y += 30.0;
+        }
+
+        let text = format!("Water: {}", view.water.floor());
+        let color = if view.water < view.rules.hydration_threshold {
+            Color::RED
+        } else {
+            Color::WHITE
+        };
+        self.geng
+            .default_font()
+            .draw(framebuffer, &text, vec2(20.0, y), 25.0, color);
+        y += 30.0;
+
+        let text = format!("Drilling (D): {}", if view.drilling { "on" } else { "off" });
         self.geng
             .default_font()
-            .draw(framebuffer, &text, vec2(20.0, 20.0), 25.0, Color::WHITE);
+            .draw(framebuffer, &text, vec2(20.0, y), 15.0, Color::GRAY);
+        y += 20.0;
+
+        if let Some(attractor_rules) = view.rules.attractor(self.attractor_kind) {
+            let cost = attractor_rules.cost;
+            let text = format!(
+                "{} (Z/X/C/V) - N {} P {} K {}",
+                self.attractor_kind.name(),
+                cost.nitrogen,
+                cost.phosphorus,
+                cost.potassium,
+            );
+            let color = if view.nutrients.covers(&cost) {
+                Color::GRAY
+            } else {
+                Color::RED
+            };
+            self.geng
+                .default_font()
+                .draw(framebuffer, &text, vec2(20.0, y), 15.0, color);
+        }
+
+        let text = format!("Seed: {}", view.seed);
+        self.geng.default_font().draw_aligned(
+            framebuffer,
+            &text,
+            vec2(size.x - 20.0, 20.0),
+            1.0,
+            15.0,
+            Color::GRAY,
+        );
 
         let text = format!("Score: {}", view.current_depth.floor());
         self.geng.default_font().draw_aligned(
@@ -127,25 +206,242 @@ impl Renderer {
             Color::WHITE,
         );
 
+        let best = high_scores.best().map_or(0.0, |score| score.depth);
+        let seed_best = high_scores
+            .best_for_seed(view.seed)
+            .map_or(0.0, |score| score.depth);
+        let text = format!("Best: {} (seed: {})", best.floor(), seed_best.floor());
+        self.geng.default_font().draw_aligned(
+            framebuffer,
+            &text,
+            vec2(size.x / 2.0, size.y - 75.0),
+            0.5,
+            15.0,
+            Color::GRAY,
+        );
+
+        self.draw_upgrades(framebuffer, view);
+
+        if view.game_over {
+            self.draw_summary(framebuffer, view);
+        }
+        if view.game_over || self.show_high_scores {
+            self.draw_high_scores(framebuffer, high_scores);
+        }
+
         let overflow = self.texture_offset + (self.texture_buffer - 2) as f32 * size.y;
         if self.target_depth > overflow / self.scale() {
             self.texture_offset += (self.texture_buffer - 3) as f32 * size.y;
             self.gen_texture(texture); //, framebuffer);
         }
     }
+    fn draw_attractors(&self, framebuffer: &mut ugli::Framebuffer, view: &model::ClientView) {
+        for attractor in &view.attractors {
+            let position = self.world_to_camera(attractor.position);
+            let (color, size) = match attractor.kind {
+                model::AttractorKind::Weak => (Color::BLUE, self.attractor_size),
+                model::AttractorKind::Strong => {
+                    (Color::rgb(0.3, 0.3, 1.0), self.attractor_size * 2.0)
+                }
+                model::AttractorKind::Repulsor => (Color::RED, self.attractor_size * 2.0),
+                model::AttractorKind::Timed => (Color::CYAN, self.attractor_size * 1.5),
+            };
+            // Timed attractors shrink as they run out
+            let fraction = match (
+                attractor.time_left,
+                view.rules
+                    .attractor(attractor.kind)
+                    .and_then(|attractor_rules| attractor_rules.lifetime),
+            ) {
+                (Some(time_left), Some(lifetime)) if lifetime > 0.0 => {
+                    (time_left / lifetime).clamp(0.2, 1.0)
+                }
+                _ => 1.0,
+            };
+            self.geng
+                .draw_2d()
+                .circle(framebuffer, position, size * fraction * self.scale, color);
+        }
+    }
+    fn draw_summary(&self, framebuffer: &mut ugli::Framebuffer, view: &model::ClientView) {
+        let size = self.screen_size.map(|x| x as f32);
+        self.geng.draw_2d().quad(
+            framebuffer,
+            AABB::pos_size(vec2(0.0, 0.0), size),
+            Color::rgba(0.0, 0.0, 0.0, 0.7),
+        );
+        let lines = [
+            ("Game over".to_owned(), 50.0),
+            (
+                format!("Depth reached: {}", view.current_depth.floor()),
+                25.0,
+            ),
+            (
+                format!(
+                    "Minerals gathered: {}",
+                    view.stats.minerals_gathered.floor()
+                ),
+                25.0,
+            ),
+            (
+                format!("Branches grown: {}", view.stats.branches_grown),
+                25.0,
+            ),
+            ("Press R to restart".to_owned(), 25.0),
+        ];
+        let mut y = size.y / 2.0 + 100.0;
+        for (text, font_size) in &lines {
+            y -= font_size * 1.5;
+            self.geng.default_font().draw_aligned(
+                framebuffer,
+                text,
+                vec2(size.x / 2.0, y),
+                0.5,
+                *font_size,
+                Color::WHITE,
+            );
+        }
+    }
+    fn draw_upgrades(&self, framebuffer: &mut ugli::Framebuffer, view: &model::ClientView) {
+        let size = self.screen_size.map(|x| x as f32);
+        let font_size = 15.0;
+        let mut y = 45.0 + font_size * 1.5 * model::Upgrade::ALL.len() as f32;
+        self.geng.default_font().draw_aligned(
+            framebuffer,
+            "Upgrades",
+            vec2(size.x - 20.0, y),
+            1.0,
+            font_size * 1.5,
+            Color::WHITE,
+        );
+        for (index, &upgrade) in model::Upgrade::ALL.iter().enumerate() {
+            let upgrade_rules = match view
+                .rules
+                .upgrades
+                .iter()
+                .find(|upgrade_rules| upgrade_rules.upgrade == upgrade)
+            {
+                Some(upgrade_rules) => upgrade_rules,
+                None => continue,
+            };
+            y -= font_size * 1.5;
+            let level = view.upgrade_levels.get(&upgrade).copied().unwrap_or(0);
+            let (text, color) = if level >= upgrade_rules.max_level {
+                (
+                    format!(
+                        "{}. {} {}/{} - max",
+                        index + 1,
+                        upgrade.name(),
+                        level,
+                        level
+                    ),
+                    Color::GRAY,
+                )
+            } else {
+                let cost = upgrade_rules.cost(level);
+                let color = if view.nutrients.covers(&cost) {
+                    Color::WHITE
+                } else {
+                    Color::GRAY
+                };
+                (
+                    format!(
+                        "{}. {} {}/{} - N {} P {} K {}",
+                        index + 1,
+                        upgrade.name(),
+                        level,
+                        upgrade_rules.max_level,
+                        cost.nitrogen.ceil(),
+                        cost.phosphorus.ceil(),
+                        cost.potassium.ceil(),
+                    ),
+                    color,
+                )
+            };
+            self.geng.default_font().draw_aligned(
+                framebuffer,
+                &text,
+                vec2(size.x - 20.0, y),
+                1.0,
+                font_size,
+                color,
+            );
+        }
+    }
+    fn draw_high_scores(&self, framebuffer: &mut ugli::Framebuffer, high_scores: &HighScores) {
+        let size = self.screen_size.map(|x| x as f32);
+        let font_size = 15.0;
+        let mut y = size.y - 120.0;
+        self.geng.default_font().draw(
+            framebuffer,
+            "High scores",
+            vec2(20.0, y),
+            font_size * 1.5,
+            Color::WHITE,
+        );
+        for (place, score) in high_scores.top(10).iter().enumerate() {
+            y -= font_size * 1.5;
+            let text = format!(
+                "{}. {} - minerals {}, seed {}, {}s, {}",
+                place + 1,
+                score.depth.floor(),
+                score.minerals.floor(),
+                score.seed,
+                score.duration.floor(),
+                score.date,
+            );
+            self.geng.default_font().draw(
+                framebuffer,
+                &text,
+                vec2(20.0, y),
+                font_size,
+                Color::WHITE,
+            );
+        }
+    }
     fn draw_impl(&mut self, framebuffer: &mut ugli::Framebuffer, view: &model::ClientView) {
         self.target_depth = view.current_depth;
 
         for (pos, tile) in &view.tiles {
             match tile {
                 model::ViewEvent::Changed(tile) => {
+                    let biomes = &view.rules.biomes;
+                    let sample = model::sample_biomes(biomes, view.rules.biome_blend, pos.y as f32);
+                    let palette = biomes[sample.from]
+                        .palette
+                        .lerp(&biomes[sample.to].palette, sample.t);
+                    let richness = {
+                        let from = biomes[sample.from].richness(tile).unwrap_or(1.0);
+                        let to = biomes[sample.to].richness(tile).unwrap_or(from);
+                        from + (to - from) * sample.t
+                    };
                     let color = match tile {
-                        Tile::Stone => Color::GRAY,
-                        Tile::Dirt => Color::rgb(0.5, 0.5, 0.0),
-                        Tile::Mineral { minerals } => Color::rgb(
-                            0.1,
-                            0.1,
-                            (minerals / view.rules.mineral_richness).clamp(0.0, 1.0),
+                        Tile::Stone => palette.stone,
+                        Tile::Dirt => palette.dirt,
+                        Tile::Clay => palette.clay,
+                        Tile::Gravel => palette.gravel,
+                        Tile::Bedrock => palette.bedrock,
+                        Tile::Toxic => palette.toxic,
+                        Tile::Air => palette.air,
+                        Tile::Nitrogen { amount } => model::lerp_color(
+                            Color::rgb(0.1, 0.1, 0.0),
+                            palette.nitrogen,
+                            (amount / richness).clamp(0.0, 1.0),
+                        ),
+                        Tile::Phosphorus { amount } => model::lerp_color(
+                            Color::rgb(0.1, 0.1, 0.0),
+                            palette.phosphorus,
+                            (amount / richness).clamp(0.0, 1.0),
+                        ),
+                        Tile::Potassium { amount } => model::lerp_color(
+                            Color::rgb(0.1, 0.1, 0.0),
+                            palette.potassium,
+                            (amount / richness).clamp(0.0, 1.0),
+                        ),
+                        Tile::Water { amount } => model::lerp_color(
+                            Color::rgb(0.0, 0.0, 0.4),
+                            palette.water,
+                            (amount / richness).clamp(0.0, 1.0),
                         ),
                     };
                     let local_pos = self.world_to_texture(pos.map(|x| x as f32));
@@ -158,9 +454,14 @@ impl Renderer {
             }
         }
 
-        for root in view.roots.values() {
+        for (&id, root) in &view.roots {
             match root {
                 model::ViewEvent::Changed(root) => {
+                    if let model::RootType::Head { .. } = root.root_type {
+                        self.heads.insert(id, root.position);
+                    } else {
+                        self.heads.remove(&id);
+                    }
                     let color = Color::rgb(0.2, 0.2, 0.0);
                     let local_pos = self.world_to_texture(root.position);
                     if let Some((_, parent_pos)) = root.parent_root {
@@ -187,31 +488,63 @@ impl Renderer {
                 }
             }
         }
-
-        for attractor in &view.attractors {
-            match attractor {
-                model::ViewEvent::Changed(attractor) => {
-                    let color = Color::BLUE;
-                    let local_pos = self.world_to_texture(attractor.position);
-                    self.geng
-                        .draw_2d()
-                        .circle(framebuffer, local_pos, self.attractor_size, color);
-                }
-            }
-        }
     }
     pub fn handle_event(&mut self, event: &geng::Event) -> Option<Message> {
         match event {
             geng::Event::MouseDown { position, button } => match button {
-                geng::MouseButton::Left => Some(Message::SplitRoot),
+                geng::MouseButton::Left => {
+                    let pos = self.camera_to_world(position.map(|x| x as f32));
+                    Some(match self.closest_head(pos) {
+                        Some(id) => Message::SplitHead { id },
+                        None => Message::SplitRoot,
+                    })
+                }
                 geng::MouseButton::Right => Some(Message::SpawnAttractor {
                     pos: self.camera_to_world(position.map(|x| x as f32)),
+                    kind: self.attractor_kind,
                 }),
                 _ => None,
             },
+            geng::Event::KeyDown { key: geng::Key::H } => {
+                self.show_high_scores = !self.show_high_scores;
+                None
+            }
+            geng::Event::KeyDown { key: geng::Key::D } => Some(Message::ToggleDrilling),
+            geng::Event::KeyDown {
+                key: key @ (geng::Key::Z | geng::Key::X | geng::Key::C | geng::Key::V),
+            } => {
+                self.attractor_kind = match key {
+                    geng::Key::Z => model::AttractorKind::Weak,
+                    geng::Key::X => model::AttractorKind::Strong,
+                    geng::Key::C => model::AttractorKind::Repulsor,
+                    _ => model::AttractorKind::Timed,
+                };
+                None
+            }
+            geng::Event::KeyDown { key } => {
+                let index = match key {
+                    geng::Key::Num1 => 0,
+                    geng::Key::Num2 => 1,
+                    geng::Key::Num3 => 2,
+                    geng::Key::Num4 => 3,
+                    _ => return None,
+                };
+                Some(Message::BuyUpgrade {
+                    upgrade: model::Upgrade::ALL[index],
+                })
+            }
             _ => None,
         }
     }
+    /// The head within `SPLIT_HEAD_RADIUS` of the position, if any.
+    fn closest_head(&self, pos: Vec2<f32>) -> Option<model::Id> {
+        self.heads
+            .iter()
+            .map(|(&id, &head_pos)| (id, (head_pos - pos).len()))
+            .filter(|&(_, distance)| distance <= SPLIT_HEAD_RADIUS)
+            .min_by(|(a_id, a), (b_id, b)| a.partial_cmp(b).unwrap().then(a_id.cmp(b_id)))
+            .map(|(id, _)| id)
+    }
     fn world_to_texture(&self, pos: Vec2<f32>) -> Vec2<f32> {
         pos * self.scale() + vec2(self.texture_center.x, -self.texture_offset)
     }
@@ -227,6 +560,14 @@ impl Renderer {
             pos.y * self.screen_size.y as f32 / DEFAULT_SCREEN_SIZE.y as f32,
         )
     }
+    fn world_to_camera(&self, pos: Vec2<f32>) -> Vec2<f32> {
+        let pos = (vec2(pos.x, -pos.y) - self.offset()) * self.scale()
+            + vec2(self.texture_center.x, self.texture_center.y * 1.5);
+        vec2(
+            pos.x * self.screen_size.x as f32 / DEFAULT_SCREEN_SIZE.x as f32,
+            pos.y * self.screen_size.y as f32 / DEFAULT_SCREEN_SIZE.y as f32,
+        )
+    }
     fn camera_to_world(&self, pos: Vec2<f32>) -> Vec2<f32> {
         let pos = vec2(
             pos.x * DEFAULT_SCREEN_SIZE.x as f32 / self.screen_size.x as f32,